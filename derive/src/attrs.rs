@@ -0,0 +1,37 @@
+use syn::{Attribute, Error, Lit, LitStr, Meta, NestedMeta, Result};
+
+/// Collect the items from every `#[name(...)]` attribute.
+pub(crate) fn nested_items(
+    attrs: &[Attribute],
+    name: &str,
+) -> Result<Vec<NestedMeta>> {
+    let mut items = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(name)) {
+        match attr.parse_meta()? {
+            Meta::List(list) => items.extend(list.nested),
+            other => {
+                let msg = format!("expected #[{}(...)]", name);
+                return Err(Error::new_spanned(other, msg));
+            },
+        }
+    }
+
+    Ok(items)
+}
+
+/// The attributes which should be copied onto a generated item.
+pub(crate) fn forwarded(
+    attrs: &[Attribute],
+) -> impl Iterator<Item = &Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde") || attr.path.is_ident("doc"))
+}
+
+pub(crate) fn lit_str(lit: &Lit) -> Result<&LitStr> {
+    match lit {
+        Lit::Str(s) => Ok(s),
+        other => Err(Error::new_spanned(other, "expected a string literal")),
+    }
+}
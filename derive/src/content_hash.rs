@@ -0,0 +1,126 @@
+use crate::{
+    serde_attrs::{RenameRule, SerdeAttrs},
+    walk_spans,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Data, DeriveInput, Error, Fields, Path, Result};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let container = SerdeAttrs::from_attrs(&input.attrs)?;
+    let type_name = name.to_string();
+
+    let arms = match &input.data {
+        Data::Struct(s) => {
+            let arm = arm(
+                parse_quote!(#name),
+                &s.fields,
+                container.rename_all,
+                quote!(::core::option::Option::None),
+            )?;
+            vec![arm]
+        },
+        Data::Enum(e) => e
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                let variant_name = ident.to_string();
+                let rename_all =
+                    SerdeAttrs::from_attrs(&variant.attrs)?.rename_all;
+                arm(
+                    parse_quote!(#name::#ident),
+                    &variant.fields,
+                    rename_all,
+                    quote!(::core::option::Option::Some(#variant_name)),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "ContentHash can't be derived for unions",
+            ))
+        },
+    };
+
+    let body = if arms.is_empty() {
+        quote!(match *self {})
+    } else {
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    let type_params: Vec<_> =
+        generics.type_params().map(|p| p.ident.clone()).collect();
+    let predicates = &mut generics.make_where_clause().predicates;
+
+    for ident in type_params {
+        predicates.push(parse_quote!(#ident: ::serde_spanned::ContentHash));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::serde_spanned::ContentHash for #name #ty_generics
+            #where_clause
+        {
+            fn content_hash(&self, state: &mut dyn ::core::hash::Hasher) {
+                let __type_name = #type_name;
+                #body
+            }
+        }
+    })
+}
+
+/// Generate the match arm which hashes a struct or enum variant's fields.
+fn arm(
+    path: Path,
+    fields: &Fields,
+    rename_all: Option<RenameRule>,
+    variant: TokenStream,
+) -> Result<TokenStream> {
+    let mut patterns = Vec::new();
+    let mut hashes = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let serde = SerdeAttrs::from_attrs(&field.attrs)?;
+        let binding = format_ident!("__field{}", i);
+        let skip = serde.skip || walk_spans::has_skip(field)?;
+
+        let pattern = if skip { quote!(_) } else { quote!(#binding) };
+        patterns.push(match &field.ident {
+            Some(ident) => quote!(#ident: #pattern),
+            None => pattern,
+        });
+
+        if skip {
+            continue;
+        }
+
+        let name = walk_spans::field_name(i, field, &serde, rename_all);
+        hashes.push(quote! {
+            __hasher.field(#name, #binding);
+        });
+    }
+
+    let pattern = match fields {
+        Fields::Named(_) => quote!(#path { #(#patterns),* }),
+        Fields::Unnamed(_) => quote!(#path ( #(#patterns),* )),
+        Fields::Unit => quote!(#path),
+    };
+
+    Ok(quote! {
+        #pattern => {
+            let mut __hasher =
+                ::serde_spanned::__StructHasher::new(state, __type_name, #variant);
+            #(#hashes)*
+            __hasher.end();
+        }
+    })
+}
@@ -0,0 +1,112 @@
+use crate::serde_attrs::SerdeAttrs;
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{Attribute, Data, DeriveInput, Error, Fields, Lit, Meta, Result};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(Error::new_spanned(
+                input,
+                "FieldsMeta can only be derived for structs with named fields",
+            )),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "FieldsMeta can only be derived for structs",
+            ))
+        },
+    };
+
+    let container = SerdeAttrs::from_attrs(&input.attrs)?;
+    let mut metas = Vec::new();
+
+    for field in fields {
+        let serde = SerdeAttrs::from_attrs(&field.attrs)?;
+        if serde.skip || serde.flatten {
+            continue;
+        }
+
+        let name = serde.rename.unwrap_or_else(|| {
+            let ident = field.ident.as_ref().unwrap().to_string();
+            let ident = ident.trim_start_matches("r#");
+            match container.rename_all {
+                Some(rule) => rule.apply_to_field(ident),
+                None => ident.to_string(),
+            }
+        });
+        let aliases = &serde.aliases;
+        let ty = type_name(&field.ty);
+        let docs = docs(&field.attrs);
+
+        metas.push(quote! {
+            ::serde_spanned::meta::FieldMeta {
+                name: #name,
+                aliases: &[#(#aliases),*],
+                ty: #ty,
+                docs: #docs,
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::serde_spanned::FieldsMeta for #name #ty_generics
+            #where_clause
+        {
+            const FIELDS: &'static [::serde_spanned::meta::FieldMeta] = &[
+                #(#metas),*
+            ];
+        }
+    })
+}
+
+/// Print a type the way a human would write it (`Vec<String>` rather than
+/// `Vec < String >`).
+fn type_name(ty: &syn::Type) -> String {
+    let tokens = ty.to_token_stream().to_string();
+    let chars: Vec<char> = tokens.chars().collect();
+    let mut name = String::new();
+
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch != ' ' {
+            name.push(ch);
+            if ch == ',' {
+                name.push(' ');
+            }
+            continue;
+        }
+
+        let is_word = |c: Option<&char>| {
+            c.map_or(false, |c| c.is_alphanumeric() || *c == '_')
+        };
+        if is_word(chars.get(i.wrapping_sub(1))) && is_word(chars.get(i + 1)) {
+            name.push(' ');
+        }
+    }
+
+    name.replace("->", " -> ")
+}
+
+/// Join the lines of a doc comment, removing the space rustdoc puts after
+/// `///`.
+fn docs(attrs: &[Attribute]) -> String {
+    let mut lines = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("doc")) {
+        if let Ok(Meta::NameValue(nv)) = attr.parse_meta() {
+            if let Lit::Str(s) = nv.lit {
+                let line = s.value();
+                let line = line.strip_prefix(' ').unwrap_or(&line);
+                lines.push(line.to_string());
+            }
+        }
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,192 @@
+//! Procedural macros for the `serde-spanned` crate.
+//!
+//! You probably want to use these through `serde_spanned` with the `derive`
+//! feature enabled instead of depending on this crate directly.
+
+extern crate proc_macro;
+
+mod attrs;
+mod content_hash;
+mod fields_meta;
+mod respan;
+mod serde_attrs;
+mod spanned_fields;
+mod unspanned;
+mod walk_spans;
+mod with_spans;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Generate a companion struct where every field is wrapped in `Spanned`.
+///
+/// Given a `struct Config { port: u16 }`, this generates a
+/// `struct SpannedConfig { port: Spanned<u16> }` which derives
+/// `serde::Deserialize`, plus `From` conversions in both directions.
+/// Converting from the plain struct gives every field an empty `0..0` span.
+///
+/// `#[serde(...)]` and doc attributes are copied onto the companion struct.
+/// The companion struct also implements `Unspanned`, converting back into
+/// the original struct, and `Respan` for going the other way.
+///
+/// # Attributes
+///
+/// On the struct:
+///
+/// - `#[spanned_fields(name = "ConfigSpans")]` - use a different name for the
+///   companion struct
+/// - `#[spanned_fields(derive(Debug, Clone))]` - derive extra traits on the
+///   companion struct
+///
+/// On a field:
+///
+/// - `#[spanned_fields(nested)]` - the field is itself a struct deriving
+///   `SpannedFields`, so use its companion (e.g. `Spanned<SpannedServer>`). The
+///   struct may be inside an `Option`, `Box`, `Vec`, `VecDeque`, or the value
+///   of a `BTreeMap` or `HashMap` (e.g. `Vec<config::Server>` becomes
+///   `Vec<config::SpannedServer>`)
+/// - `#[spanned_fields(nested = "ServerSpans")]` - the same, but with an
+///   explicit companion type, which must implement `Respan`
+#[proc_macro_derive(SpannedFields, attributes(spanned_fields, serde))]
+pub fn derive_spanned_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    spanned_fields::expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implement `FieldsMeta` for a struct with named fields.
+///
+/// Each field is listed under the name it has in the serialized document,
+/// taking `#[serde(rename = "...")]` and `#[serde(rename_all = "...")]` into
+/// account, along with any `#[serde(alias = "...")]`, its type, and its doc
+/// comment. Skipped and flattened fields are left out.
+#[proc_macro_derive(FieldsMeta, attributes(serde))]
+pub fn derive_fields_meta(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    fields_meta::expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implement `Unspanned` for a struct or enum, converting it into an
+/// equivalent type without any `Spanned` wrappers.
+///
+/// The plain type must be given with `#[unspanned(plain = "...")]`. It needs
+/// the same fields (or variants) as the original, where each field's type is
+/// the original field's `Unspanned::Plain` type.
+///
+/// ```rust,ignore
+/// #[derive(Unspanned)]
+/// #[unspanned(plain = "Server")]
+/// struct SpannedServer {
+///     host: Spanned<String>,
+///     ports: Vec<Spanned<u16>>,
+/// }
+///
+/// struct Server {
+///     host: String,
+///     ports: Vec<u16>,
+/// }
+/// ```
+///
+/// Companion structs generated by `#[derive(SpannedFields)]` already
+/// implement `Unspanned`.
+#[proc_macro_derive(Unspanned, attributes(unspanned))]
+pub fn derive_unspanned(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    unspanned::expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implement `Respan` for a struct or enum, the inverse of
+/// `#[derive(Unspanned)]`.
+///
+/// The plain type is given with the same `#[unspanned(plain = "...")]`
+/// attribute, and fields are looked up using the same paths as
+/// `#[derive(WalkSpans)]`.
+///
+/// ```rust,ignore
+/// #[derive(Unspanned, Respan, WalkSpans)]
+/// #[unspanned(plain = "Server")]
+/// struct SpannedServer {
+///     host: Spanned<String>,
+///     ports: Vec<Spanned<u16>>,
+/// }
+/// ```
+#[proc_macro_derive(Respan, attributes(unspanned, serde))]
+pub fn derive_respan(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    respan::expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implement `WalkSpans` for a struct or enum.
+///
+/// Each field is walked with a path segment named after the field, taking
+/// `#[serde(rename = "...")]` and `#[serde(rename_all = "...")]` into account
+/// so paths match the keys in the original document. Flattened fields and
+/// the field of a newtype are walked without adding a segment.
+///
+/// The fields of an externally tagged enum variant are under a segment named
+/// after the variant (e.g. `Circle.radius`), and those of an adjacently
+/// tagged variant are under the `content` key. Internally tagged and
+/// untagged variants don't add a segment.
+///
+/// Fields marked with `#[walk_spans(skip)]`, `#[serde(skip)]`, or
+/// `#[serde(skip_deserializing)]` are ignored.
+#[proc_macro_derive(WalkSpans, attributes(walk_spans, serde))]
+pub fn derive_walk_spans(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    walk_spans::expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implement `ContentHash` for a struct or enum, hashing every field while
+/// ignoring spans.
+///
+/// Fields are skipped the same way as `#[derive(WalkSpans)]`, with
+/// `#[walk_spans(skip)]`, `#[serde(skip)]`, or
+/// `#[serde(skip_deserializing)]`.
+#[proc_macro_derive(ContentHash, attributes(walk_spans, serde))]
+pub fn derive_content_hash(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    content_hash::expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Wrap individual fields of a struct in `Spanned`.
+///
+/// Fields marked with `#[spanned]` have their type changed from `T` to
+/// `Spanned<T>`, and two accessors are generated for each of them: `field()`
+/// returning `&T` and `field_span()` returning the span.
+///
+/// This attribute must be placed above `#[derive(Deserialize)]`.
+///
+/// ```rust,ignore
+/// #[serde_spanned::with_spans]
+/// #[derive(serde::Deserialize)]
+/// struct Config {
+///     #[spanned]
+///     password_file: PathBuf,
+///     retries: u32,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn with_spans(args: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    with_spans::expand(args.into(), input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,144 @@
+use crate::{
+    serde_attrs::{RenameRule, SerdeAttrs},
+    unspanned, walk_spans,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Data, DeriveInput, Error, Fields, Path, Result};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let plain = unspanned::plain_type(input)?;
+    let container = SerdeAttrs::from_attrs(&input.attrs)?;
+
+    let (arms, field_types) = match &input.data {
+        Data::Struct(s) => {
+            let (arm, types) = arm(
+                unspanned::plain_path(&plain)?,
+                parse_quote!(#name),
+                &s.fields,
+                container.rename_all,
+                None,
+            )?;
+            (vec![arm], types)
+        },
+        Data::Enum(e) => {
+            let mut arms = Vec::new();
+            let mut types = Vec::new();
+
+            for variant in &e.variants {
+                let ident = &variant.ident;
+                let serde = SerdeAttrs::from_attrs(&variant.attrs)?;
+                let (arm, variant_types) = arm(
+                    unspanned::plain_variant(&plain, ident)?,
+                    parse_quote!(#name::#ident),
+                    &variant.fields,
+                    serde.rename_all,
+                    walk_spans::variant_segment(&container, ident, &serde),
+                )?;
+                arms.push(arm);
+                types.extend(variant_types);
+            }
+
+            (arms, types)
+        },
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "Respan can't be derived for unions",
+            ))
+        },
+    };
+
+    let body = if arms.is_empty() {
+        quote!(match plain {})
+    } else {
+        quote! {
+            #[allow(unused_variables)]
+            match plain {
+                #(#arms)*
+            }
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    let predicates = &mut generics.make_where_clause().predicates;
+    for ty in field_types {
+        predicates.push(parse_quote!(#ty: ::serde_spanned::Respan));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::serde_spanned::Respan for #name #ty_generics
+            #where_clause
+        {
+            fn respan(
+                plain: Self::Plain,
+                path: &::serde_spanned::walk::Path<'_>,
+                spans: &mut dyn ::core::ops::FnMut(
+                    &::serde_spanned::walk::Path<'_>,
+                ) -> ::core::option::Option<::serde_spanned::Span>,
+            ) -> Self {
+                #body
+            }
+        }
+    })
+}
+
+/// Generate the match arm which converts a plain struct or variant back into
+/// `path`, along with the types of its fields.
+fn arm(
+    plain: Path,
+    path: Path,
+    fields: &Fields,
+    rename_all: Option<RenameRule>,
+    variant: Option<String>,
+) -> Result<(TokenStream, Vec<syn::Type>)> {
+    let bindings: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect();
+    let newtype = fields.len() == 1;
+    let mut types = Vec::new();
+    let mut respans = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let serde = SerdeAttrs::from_attrs(&field.attrs)?;
+        let child_path =
+            walk_spans::child_path(i, field, &serde, rename_all, newtype);
+        let binding = &bindings[i];
+        let ty = &field.ty;
+
+        respans.push(quote! {
+            <#ty as ::serde_spanned::Respan>::respan(#binding, #child_path, spans)
+        });
+        types.push(ty.clone());
+    }
+
+    let variant = variant.map(|name| {
+        quote! {
+            let path = &path.join(::serde_spanned::walk::Segment::Field(#name));
+        }
+    });
+
+    let arm = match fields {
+        Fields::Named(named) => {
+            let idents: Vec<_> = named.named.iter().map(|f| &f.ident).collect();
+            quote! {
+                #plain { #(#idents: #bindings),* } => {
+                    #variant
+                    #path { #(#idents: #respans),* }
+                },
+            }
+        },
+        Fields::Unnamed(_) => quote! {
+            #plain ( #(#bindings),* ) => {
+                #variant
+                #path ( #(#respans),* )
+            },
+        },
+        Fields::Unit => quote!(#plain => #path,),
+    };
+
+    Ok((arm, types))
+}
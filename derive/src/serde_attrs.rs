@@ -0,0 +1,196 @@
+//! Just enough of serde's attributes to know what a field will be called in
+//! the serialized document.
+
+use crate::attrs;
+use syn::{Attribute, Error, Lit, Meta, NestedMeta, Result};
+
+/// The options from `#[serde(...)]` attributes we care about.
+#[derive(Default)]
+pub(crate) struct SerdeAttrs {
+    pub(crate) rename: Option<String>,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) rename_all: Option<RenameRule>,
+    pub(crate) skip: bool,
+    pub(crate) flatten: bool,
+    pub(crate) tag: Option<String>,
+    pub(crate) content: Option<String>,
+    pub(crate) untagged: bool,
+}
+
+impl SerdeAttrs {
+    pub(crate) fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut serde = SerdeAttrs::default();
+
+        for item in attrs::nested_items(attrs, "serde")? {
+            let meta = match item {
+                NestedMeta::Meta(meta) => meta,
+                NestedMeta::Lit(_) => continue,
+            };
+
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("rename") => {
+                    serde.rename = Some(attrs::lit_str(&nv.lit)?.value());
+                },
+                Meta::List(list) if list.path.is_ident("rename") => {
+                    if let Some(lit) = deserialize_value(&list.nested) {
+                        serde.rename = Some(attrs::lit_str(lit)?.value());
+                    }
+                },
+                Meta::NameValue(nv) if nv.path.is_ident("alias") => {
+                    serde.aliases.push(attrs::lit_str(&nv.lit)?.value());
+                },
+                Meta::NameValue(nv) if nv.path.is_ident("rename_all") => {
+                    serde.rename_all = Some(RenameRule::from_lit(&nv.lit)?);
+                },
+                Meta::List(list) if list.path.is_ident("rename_all") => {
+                    if let Some(lit) = deserialize_value(&list.nested) {
+                        serde.rename_all = Some(RenameRule::from_lit(lit)?);
+                    }
+                },
+                Meta::Path(path)
+                    if path.is_ident("skip")
+                        || path.is_ident("skip_deserializing") =>
+                {
+                    serde.skip = true;
+                },
+                Meta::Path(path) if path.is_ident("flatten") => {
+                    serde.flatten = true;
+                },
+                Meta::NameValue(nv) if nv.path.is_ident("tag") => {
+                    serde.tag = Some(attrs::lit_str(&nv.lit)?.value());
+                },
+                Meta::NameValue(nv) if nv.path.is_ident("content") => {
+                    serde.content = Some(attrs::lit_str(&nv.lit)?.value());
+                },
+                Meta::Path(path) if path.is_ident("untagged") => {
+                    serde.untagged = true;
+                },
+                _ => {},
+            }
+        }
+
+        Ok(serde)
+    }
+}
+
+/// Find the `deserialize = "..."` in something like
+/// `rename(serialize = "a", deserialize = "b")`.
+fn deserialize_value<'a, I>(nested: I) -> Option<&'a Lit>
+where
+    I: IntoIterator<Item = &'a NestedMeta>,
+{
+    nested.into_iter().find_map(|item| match item {
+        NestedMeta::Meta(Meta::NameValue(nv))
+            if nv.path.is_ident("deserialize") =>
+        {
+            Some(&nv.lit)
+        },
+        _ => None,
+    })
+}
+
+/// The case conventions accepted by `#[serde(rename_all = "...")]`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_lit(lit: &Lit) -> Result<Self> {
+        let lit = attrs::lit_str(lit)?;
+
+        match lit.value().as_str() {
+            "lowercase" => Ok(RenameRule::Lower),
+            "UPPERCASE" => Ok(RenameRule::Upper),
+            "PascalCase" => Ok(RenameRule::Pascal),
+            "camelCase" => Ok(RenameRule::Camel),
+            "snake_case" => Ok(RenameRule::Snake),
+            "SCREAMING_SNAKE_CASE" => Ok(RenameRule::ScreamingSnake),
+            "kebab-case" => Ok(RenameRule::Kebab),
+            "SCREAMING-KEBAB-CASE" => Ok(RenameRule::ScreamingKebab),
+            _ => Err(Error::new_spanned(lit, "unknown rename rule")),
+        }
+    }
+
+    /// Rename a `snake_case` field.
+    pub(crate) fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => {
+                field.to_ascii_uppercase()
+            },
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+
+                pascal
+            },
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply_to_field(field);
+                lowercase_first(&pascal)
+            },
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => {
+                field.to_ascii_uppercase().replace('_', "-")
+            },
+        }
+    }
+
+    /// Rename a `PascalCase` variant.
+    pub(crate) fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => lowercase_first(variant),
+            RenameRule::Snake => {
+                let mut snake = String::new();
+
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+
+                snake
+            },
+            RenameRule::ScreamingSnake => RenameRule::Snake
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+}
+
+fn lowercase_first(s: &str) -> String {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{attrs, serde_attrs::SerdeAttrs, walk_spans};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Error, Fields, GenericArgument, Ident, Member, Meta,
+    NestedMeta, Path, PathArguments, PathSegment, Result, Type,
+};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(s) => &s.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "SpannedFields can only be derived for structs",
+            ))
+        },
+    };
+
+    if let Fields::Unit = fields {
+        return Err(Error::new_spanned(
+            input,
+            "SpannedFields can't be derived for unit structs",
+        ));
+    }
+
+    let container = Container::from_input(input)?;
+    let fields = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| Field::new(i, field))
+        .collect::<Result<Vec<_>>>()?;
+
+    let definition = definition(input, &container, &fields);
+    let conversions = conversions(input, &container, &fields)?;
+
+    Ok(quote! {
+        #definition
+        #conversions
+    })
+}
+
+/// Options from the `#[spanned_fields(...)]` attribute on the struct.
+pub(crate) struct Container {
+    pub(crate) name: Ident,
+    derives: Vec<Path>,
+}
+
+impl Container {
+    pub(crate) fn from_input(input: &DeriveInput) -> Result<Self> {
+        let mut name = format_ident!("Spanned{}", input.ident);
+        let mut derives = Vec::new();
+
+        for item in attrs::nested_items(&input.attrs, "spanned_fields")? {
+            match item {
+                NestedMeta::Meta(Meta::NameValue(nv))
+                    if nv.path.is_ident("name") =>
+                {
+                    name = attrs::lit_str(&nv.lit)?.parse()?;
+                },
+                NestedMeta::Meta(Meta::List(list))
+                    if list.path.is_ident("derive") =>
+                {
+                    for nested in list.nested {
+                        match nested {
+                            NestedMeta::Meta(Meta::Path(path)) => {
+                                derives.push(path)
+                            },
+                            other => {
+                                return Err(Error::new_spanned(
+                                    other,
+                                    "expected the name of a trait to derive",
+                                ))
+                            },
+                        }
+                    }
+                },
+                other => {
+                    return Err(Error::new_spanned(
+                        other,
+                        "unknown spanned_fields attribute",
+                    ))
+                },
+            }
+        }
+
+        Ok(Container { name, derives })
+    }
+}
+
+/// A field on the original struct.
+pub(crate) struct Field<'a> {
+    pub(crate) member: Member,
+    pub(crate) field: &'a syn::Field,
+    /// The companion type to use for a nested struct, if any.
+    pub(crate) nested: Option<Type>,
+}
+
+impl<'a> Field<'a> {
+    pub(crate) fn new(index: usize, field: &'a syn::Field) -> Result<Self> {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(index.into()),
+        };
+        let mut nested = None;
+
+        for item in attrs::nested_items(&field.attrs, "spanned_fields")? {
+            match item {
+                NestedMeta::Meta(Meta::Path(path))
+                    if path.is_ident("nested") =>
+                {
+                    nested = Some(conventional_companion(&field.ty)?);
+                },
+                NestedMeta::Meta(Meta::NameValue(nv))
+                    if nv.path.is_ident("nested") =>
+                {
+                    nested = Some(attrs::lit_str(&nv.lit)?.parse()?);
+                },
+                other => {
+                    return Err(Error::new_spanned(
+                        other,
+                        "unknown spanned_fields attribute",
+                    ))
+                },
+            }
+        }
+
+        Ok(Field {
+            member,
+            field,
+            nested,
+        })
+    }
+
+    /// The type this field will have on the companion struct.
+    pub(crate) fn spanned_type(&self) -> TokenStream {
+        let ty = self.nested.as_ref().unwrap_or(&self.field.ty);
+        quote!(::serde_spanned::Spanned<#ty>)
+    }
+}
+
+/// Guess the companion type for a nested struct by prefixing its name with
+/// `Spanned` (e.g. `Server<T>` becomes `SpannedServer<T>`).
+///
+/// Containers are looked through, so `Vec<Server>` becomes
+/// `Vec<SpannedServer>` rather than `SpannedVec<Server>`.
+fn conventional_companion(ty: &Type) -> Result<Type> {
+    if let Type::Path(path) = ty {
+        let mut path = path.clone();
+
+        if let Some(last) = path.path.segments.last_mut() {
+            if let Some(inner) = container_element(last) {
+                *inner = conventional_companion(inner)?;
+            } else {
+                last.ident = format_ident!("Spanned{}", last.ident);
+            }
+
+            return Ok(Type::Path(path));
+        }
+    }
+
+    Err(Error::new_spanned(
+        ty,
+        "unable to infer the companion type, use #[spanned_fields(nested = \"...\")]",
+    ))
+}
+
+/// The element type, if `segment` is a container which `Unspanned` and
+/// `Respan` look inside.
+fn container_element(segment: &mut PathSegment) -> Option<&mut Type> {
+    let index = match segment.ident.to_string().as_str() {
+        "Option" | "Box" | "Vec" | "VecDeque" => 0,
+        "BTreeMap" | "HashMap" => 1,
+        _ => return None,
+    };
+
+    match &mut segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter_mut()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .nth(index),
+        _ => None,
+    }
+}
+
+fn definition(
+    input: &DeriveInput,
+    container: &Container,
+    fields: &[Field<'_>],
+) -> TokenStream {
+    let vis = &input.vis;
+    let name = &container.name;
+    let derives = &container.derives;
+    let attrs = attrs::forwarded(&input.attrs);
+    let (_, _, where_clause) = input.generics.split_for_impl();
+    let generics = &input.generics;
+
+    let field_defs = fields.iter().map(|f| {
+        let attrs = attrs::forwarded(&f.field.attrs);
+        let vis = &f.field.vis;
+        let ty = f.spanned_type();
+
+        match &f.field.ident {
+            Some(ident) => quote!(#(#attrs)* #vis #ident: #ty),
+            None => quote!(#(#attrs)* #vis #ty),
+        }
+    });
+
+    let body = if fields.iter().all(|f| f.field.ident.is_some()) {
+        quote!(#where_clause { #(#field_defs,)* })
+    } else {
+        quote!(( #(#field_defs,)* ) #where_clause;)
+    };
+
+    quote! {
+        #[derive(::serde::Deserialize #(, #derives)*)]
+        #(#attrs)*
+        #vis struct #name #generics #body
+    }
+}
+
+fn conversions(
+    input: &DeriveInput,
+    container: &Container,
+    fields: &[Field<'_>],
+) -> Result<TokenStream> {
+    let plain = &input.ident;
+    let name = &container.name;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    let container_serde = SerdeAttrs::from_attrs(&input.attrs)?;
+    let newtype = fields.len() == 1;
+
+    let members: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    let unspan = fields.iter().map(|f| {
+        let member = &f.member;
+
+        if f.nested.is_some() {
+            quote!(::serde_spanned::Unspanned::into_plain(spanned.#member))
+        } else {
+            quote!(spanned.#member.into_value())
+        }
+    });
+    let respan = fields.iter().map(|f| {
+        let member = &f.member;
+
+        if let Some(nested) = &f.nested {
+            quote! {
+                <::serde_spanned::Spanned<#nested> as ::serde_spanned::Respan>::respan(
+                    plain.#member,
+                    &::serde_spanned::walk::Path::ROOT,
+                    &mut |_| ::core::option::Option::None,
+                )
+            }
+        } else {
+            quote!(::serde_spanned::Spanned::new(plain.#member, 0, 0))
+        }
+    });
+
+    let respan_fields = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let member = &f.member;
+            let serde = SerdeAttrs::from_attrs(&f.field.attrs)?;
+            let path = walk_spans::child_path(
+                i,
+                f.field,
+                &serde,
+                container_serde.rename_all,
+                newtype,
+            );
+
+            Ok(match &f.nested {
+                Some(nested) => quote! {
+                    <::serde_spanned::Spanned<#nested> as ::serde_spanned::Respan>::respan(
+                        plain.#member,
+                        #path,
+                        spans,
+                    )
+                },
+                None => quote! {
+                    ::serde_spanned::Spanned::from_span(
+                        plain.#member,
+                        spans(#path).unwrap_or_default(),
+                    )
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<#name #ty_generics>
+            for #plain #ty_generics #where_clause
+        {
+            fn from(spanned: #name #ty_generics) -> Self {
+                #plain { #(#members: #unspan,)* }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#plain #ty_generics>
+            for #name #ty_generics #where_clause
+        {
+            fn from(plain: #plain #ty_generics) -> Self {
+                #name { #(#members: #respan,)* }
+            }
+        }
+
+        impl #impl_generics ::serde_spanned::Unspanned
+            for #name #ty_generics #where_clause
+        {
+            type Plain = #plain #ty_generics;
+
+            fn into_plain(self) -> Self::Plain {
+                ::core::convert::From::from(self)
+            }
+        }
+
+        impl #impl_generics ::serde_spanned::Respan
+            for #name #ty_generics #where_clause
+        {
+            fn respan(
+                plain: Self::Plain,
+                path: &::serde_spanned::walk::Path<'_>,
+                spans: &mut dyn ::core::ops::FnMut(
+                    &::serde_spanned::walk::Path<'_>,
+                ) -> ::core::option::Option<::serde_spanned::Span>,
+            ) -> Self {
+                #name { #(#members: #respan_fields,)* }
+            }
+        }
+    })
+}
@@ -0,0 +1,157 @@
+use crate::attrs;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Data, DeriveInput, Error, Fields, Meta, NestedMeta, Path,
+    Result,
+};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let plain = plain_type(input)?;
+
+    let (arms, field_types) = match &input.data {
+        Data::Struct(s) => {
+            let (arm, types) =
+                arm(parse_quote!(#name), plain_path(&plain)?, &s.fields);
+            (vec![arm], types)
+        },
+        Data::Enum(e) => {
+            let mut arms = Vec::new();
+            let mut types = Vec::new();
+
+            for variant in &e.variants {
+                let ident = &variant.ident;
+                let (arm, variant_types) = arm(
+                    parse_quote!(#name::#ident),
+                    plain_variant(&plain, ident)?,
+                    &variant.fields,
+                );
+                arms.push(arm);
+                types.extend(variant_types);
+            }
+
+            (arms, types)
+        },
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "Unspanned can't be derived for unions",
+            ))
+        },
+    };
+
+    let body = if arms.is_empty() {
+        quote!(match self {})
+    } else {
+        quote!(match self { #(#arms)* })
+    };
+
+    let mut generics = input.generics.clone();
+    let predicates = &mut generics.make_where_clause().predicates;
+    for ty in field_types {
+        predicates.push(parse_quote!(#ty: ::serde_spanned::Unspanned));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::serde_spanned::Unspanned for #name #ty_generics
+            #where_clause
+        {
+            type Plain = #plain;
+
+            fn into_plain(self) -> Self::Plain {
+                #body
+            }
+        }
+    })
+}
+
+/// Read the type from `#[unspanned(plain = "...")]`.
+pub(crate) fn plain_type(input: &DeriveInput) -> Result<syn::Type> {
+    let mut plain = None;
+
+    for item in attrs::nested_items(&input.attrs, "unspanned")? {
+        match item {
+            NestedMeta::Meta(Meta::NameValue(nv))
+                if nv.path.is_ident("plain") =>
+            {
+                plain = Some(attrs::lit_str(&nv.lit)?.parse()?);
+            },
+            other => {
+                return Err(Error::new_spanned(
+                    other,
+                    "unknown unspanned attribute",
+                ))
+            },
+        }
+    }
+
+    plain.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "the plain type must be given with #[unspanned(plain = \"...\")]",
+        )
+    })
+}
+
+/// Get the path to the plain type, dropping any generic arguments (e.g.
+/// `Shape<T>` gives `Shape`) so it can be used in expressions and patterns.
+pub(crate) fn plain_path(plain: &syn::Type) -> Result<Path> {
+    match plain {
+        syn::Type::Path(ty) => {
+            let mut path = ty.path.clone();
+            if let Some(last) = path.segments.last_mut() {
+                last.arguments = syn::PathArguments::None;
+            }
+            Ok(path)
+        },
+        other => {
+            Err(Error::new_spanned(other, "the plain type must be a path"))
+        },
+    }
+}
+
+/// Get the path to a variant on the plain enum (e.g. `Shape<T>` gives
+/// `Shape::Circle`).
+pub(crate) fn plain_variant(
+    plain: &syn::Type,
+    variant: &syn::Ident,
+) -> Result<Path> {
+    let mut path = plain_path(plain)?;
+    path.segments.push(variant.clone().into());
+    Ok(path)
+}
+
+/// Generate the match arm converting one struct or variant, along with the
+/// types of its fields.
+fn arm(
+    path: Path,
+    plain: Path,
+    fields: &Fields,
+) -> (TokenStream, Vec<syn::Type>) {
+    let bindings: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect();
+    let types = fields.iter().map(|f| f.ty.clone()).collect();
+
+    let arm = match fields {
+        Fields::Named(named) => {
+            let idents: Vec<_> = named.named.iter().map(|f| &f.ident).collect();
+            quote! {
+                #path { #(#idents: #bindings),* } => #plain {
+                    #(#idents: ::serde_spanned::Unspanned::into_plain(#bindings)),*
+                },
+            }
+        },
+        Fields::Unnamed(_) => quote! {
+            #path ( #(#bindings),* ) => #plain (
+                #(::serde_spanned::Unspanned::into_plain(#bindings)),*
+            ),
+        },
+        Fields::Unit => quote!(#path => #plain,),
+    };
+
+    (arm, types)
+}
@@ -0,0 +1,235 @@
+use crate::{
+    attrs,
+    serde_attrs::{RenameRule, SerdeAttrs},
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Data, DeriveInput, Error, Fields, Ident, Meta, NestedMeta,
+    Path, Result,
+};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let container = SerdeAttrs::from_attrs(&input.attrs)?;
+
+    let arms = match &input.data {
+        Data::Struct(s) => {
+            let arm = arm(
+                parse_quote!(#name),
+                &s.fields,
+                container.rename_all,
+                None,
+            )?;
+            vec![arm]
+        },
+        Data::Enum(e) => e
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                let serde = SerdeAttrs::from_attrs(&variant.attrs)?;
+                arm(
+                    parse_quote!(#name::#ident),
+                    &variant.fields,
+                    serde.rename_all,
+                    variant_segment(&container, ident, &serde),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "WalkSpans can't be derived for unions",
+            ))
+        },
+    };
+
+    let body = if arms.is_empty() {
+        quote!(match *self {})
+    } else {
+        quote! {
+            #[allow(unused_variables)]
+            match self {
+                #(#arms)*
+            }
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    let type_params: Vec<_> =
+        generics.type_params().map(|p| p.ident.clone()).collect();
+    let predicates = &mut generics.make_where_clause().predicates;
+
+    for ident in type_params {
+        predicates.push(parse_quote!(#ident: ::serde_spanned::WalkSpans));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::serde_spanned::WalkSpans for #name #ty_generics
+            #where_clause
+        {
+            fn walk_spans(
+                &self,
+                path: &::serde_spanned::walk::Path<'_>,
+                visitor: &mut dyn ::core::ops::FnMut(
+                    &::serde_spanned::walk::Path<'_>,
+                    (usize, usize),
+                ),
+            ) {
+                #body
+            }
+        }
+    })
+}
+
+/// Generate the match arm which walks a struct or enum variant's fields,
+/// which are inside the `variant` field when there is one.
+fn arm(
+    path: Path,
+    fields: &Fields,
+    rename_all: Option<RenameRule>,
+    variant: Option<String>,
+) -> Result<TokenStream> {
+    let mut patterns = Vec::new();
+    let mut walks = Vec::new();
+    let newtype = fields.len() == 1;
+
+    for (i, field) in fields.iter().enumerate() {
+        let serde = SerdeAttrs::from_attrs(&field.attrs)?;
+        let binding = format_ident!("__field{}", i);
+        let skip = serde.skip || has_skip(field)?;
+
+        let pattern = if skip { quote!(_) } else { quote!(#binding) };
+        patterns.push(match &field.ident {
+            Some(ident) => quote!(#ident: #pattern),
+            None => pattern,
+        });
+
+        if skip {
+            continue;
+        }
+
+        let child_path = child_path(i, field, &serde, rename_all, newtype);
+
+        walks.push(quote! {
+            ::serde_spanned::WalkSpans::walk_spans(#binding, #child_path, visitor);
+        });
+    }
+
+    let pattern = match fields {
+        Fields::Named(_) => quote!(#path { #(#patterns),* }),
+        Fields::Unnamed(_) => quote!(#path ( #(#patterns),* )),
+        Fields::Unit => quote!(#path),
+    };
+
+    let variant = variant.map(|name| {
+        quote! {
+            let path = &path.join(::serde_spanned::walk::Segment::Field(#name));
+        }
+    });
+
+    Ok(quote! {
+        #pattern => {
+            #variant
+            #(#walks)*
+        }
+    })
+}
+
+/// The path segment between an enum and its variant's fields, which depends
+/// on how the enum is tagged.
+///
+/// Externally tagged variants (serde's default) are written as
+/// `{ "Variant": ... }` and adjacently tagged variants put their fields
+/// under the `content` key. Internally tagged and untagged variants write
+/// their fields alongside the enum's, so there's no extra segment.
+pub(crate) fn variant_segment(
+    container: &SerdeAttrs,
+    variant: &Ident,
+    serde: &SerdeAttrs,
+) -> Option<String> {
+    if container.untagged {
+        return None;
+    }
+
+    match (&container.tag, &container.content) {
+        (Some(_), Some(content)) => Some(content.clone()),
+        (Some(_), None) => None,
+        (None, _) => Some(serde.rename.clone().unwrap_or_else(|| {
+            let ident = variant.to_string();
+            let ident = ident.trim_start_matches("r#");
+            match container.rename_all {
+                Some(rule) => rule.apply_to_variant(ident),
+                None => ident.to_string(),
+            }
+        })),
+    }
+}
+
+/// The expression for a field's path, relative to a `path` variable holding
+/// the path of its parent.
+pub(crate) fn child_path(
+    index: usize,
+    field: &syn::Field,
+    serde: &SerdeAttrs,
+    rename_all: Option<RenameRule>,
+    newtype: bool,
+) -> TokenStream {
+    match &field.ident {
+        _ if serde.flatten => quote!(path),
+        Some(_) => {
+            let name = field_name(index, field, serde, rename_all);
+            quote! {
+                &path.join(::serde_spanned::walk::Segment::Field(#name))
+            }
+        },
+        None if newtype => quote!(path),
+        None => {
+            quote!(&path.join(::serde_spanned::walk::Segment::Index(#index)))
+        },
+    }
+}
+
+/// The name a field has in the serialized document, or its index for a
+/// tuple field.
+pub(crate) fn field_name(
+    index: usize,
+    field: &syn::Field,
+    serde: &SerdeAttrs,
+    rename_all: Option<RenameRule>,
+) -> String {
+    match &field.ident {
+        Some(ident) => serde.rename.clone().unwrap_or_else(|| {
+            let ident = ident.to_string();
+            let ident = ident.trim_start_matches("r#");
+            match rename_all {
+                Some(rule) => rule.apply_to_field(ident),
+                None => ident.to_string(),
+            }
+        }),
+        None => index.to_string(),
+    }
+}
+
+pub(crate) fn has_skip(field: &syn::Field) -> Result<bool> {
+    let mut skip = false;
+
+    for item in attrs::nested_items(&field.attrs, "walk_spans")? {
+        match item {
+            NestedMeta::Meta(Meta::Path(p)) if p.is_ident("skip") => {
+                skip = true;
+            },
+            other => {
+                return Err(Error::new_spanned(
+                    other,
+                    "unknown walk_spans attribute",
+                ))
+            },
+        }
+    }
+
+    Ok(skip)
+}
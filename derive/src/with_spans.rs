@@ -0,0 +1,81 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, Result};
+
+pub(crate) fn expand(
+    args: TokenStream,
+    mut input: DeriveInput,
+) -> Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(Error::new_spanned(args, "with_spans takes no arguments"));
+    }
+
+    let fields = match &mut input.data {
+        Data::Struct(s) => &mut s.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input,
+                "with_spans can only be used on structs",
+            ))
+        },
+    };
+
+    if let Fields::Unit = fields {
+        return Err(Error::new_spanned(
+            &input,
+            "with_spans can't be used on unit structs",
+        ));
+    }
+
+    let mut accessors = Vec::new();
+
+    for field in fields.iter_mut() {
+        let before = field.attrs.len();
+        field.attrs.retain(|attr| !attr.path.is_ident("spanned"));
+
+        if field.attrs.len() == before {
+            continue;
+        }
+
+        let ident = match &field.ident {
+            Some(ident) => ident.clone(),
+            None => {
+                return Err(Error::new_spanned(
+                    &field,
+                    "#[spanned] can only be used on named fields",
+                ))
+            },
+        };
+        let ty = field.ty.clone();
+        let vis = &field.vis;
+        let span_getter = format_ident!("{}_span", ident);
+        let value_doc = format!("The `{}` field's value.", ident);
+        let span_doc = format!("Where the `{}` field was defined.", ident);
+
+        accessors.push(quote! {
+            #[doc = #value_doc]
+            #vis fn #ident(&self) -> &#ty {
+                ::serde_spanned::Spanned::value(&self.#ident)
+            }
+
+            #[doc = #span_doc]
+            #vis fn #span_getter(&self) -> (usize, usize) {
+                ::serde_spanned::Spanned::span(&self.#ident)
+            }
+        });
+
+        field.ty = syn::parse_quote!(::serde_spanned::Spanned<#ty>);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    Ok(quote! {
+        #input
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#accessors)*
+        }
+    })
+}
@@ -0,0 +1,574 @@
+//! A conformance suite for formats which support [`Spanned`] values.
+//!
+//! Implement [`Format`] for your deserializer and call
+//! [`assert_conformance()`] from a test. Each check renders a small document
+//! with [`Format::render()`], deserializes it, and makes sure the spans
+//! cover the right text. The error checks make sure errors, including ones
+//! from inside nested [`Spanned`] values, say where in the source they
+//! happened.
+//!
+//! ```rust,ignore
+//! struct Toml;
+//!
+//! impl Format for Toml {
+//!     type Error = toml::de::Error;
+//!
+//!     fn render(&self, doc: &Doc) -> String { ... }
+//!
+//!     fn deserialize<T: DeserializeOwned>(
+//!         &self,
+//!         src: &str,
+//!     ) -> Result<T, Self::Error> {
+//!         toml::from_str(src)
+//!     }
+//!
+//!     fn error_offset(&self, src: &str, error: &Self::Error) -> Option<usize> {
+//!         error.span().map(|span| span.start)
+//!     }
+//! }
+//!
+//! #[test]
+//! fn spans_are_correct() { assert_conformance(&Toml); }
+//! ```
+//!
+//! The reference format in this crate's `tests/common/mod.rs` implements
+//! [`Format`] for a small JSON-like format, and passes every check with
+//! both of its deserializers.
+//!
+//! [`Spanned`]: crate::Spanned
+
+use crate::Spanned;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    format, panic,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// A document to be rendered in a particular format.
+///
+/// The top level is always a [`Doc::Map`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Doc {
+    Bool(bool),
+    Integer(i64),
+    String(&'static str),
+    Seq(Vec<Doc>),
+    /// A map with string keys, in the order they should be written.
+    Map(Vec<(&'static str, Doc)>),
+}
+
+/// A format being checked by the conformance suite.
+pub trait Format {
+    type Error: Display;
+
+    /// Write `doc` in this format.
+    fn render(&self, doc: &Doc) -> String;
+
+    fn deserialize<T: DeserializeOwned>(
+        &self,
+        src: &str,
+    ) -> Result<T, Self::Error>;
+
+    /// The byte offset in `src` which `error` points at, if it says.
+    ///
+    /// The error checks fail when this is `None`, because a diagnostic
+    /// needs to say where the problem is.
+    fn error_offset(&self, src: &str, error: &Self::Error) -> Option<usize>;
+}
+
+/// A conformance check which didn't pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub check: &'static str,
+    pub message: String,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.check, self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Run every check, returning the ones which failed.
+///
+/// A check which panics is reported as a failure, so one broken check
+/// doesn't hide the others.
+pub fn run<F: Format>(format: &F) -> Vec<Failure> {
+    let checks: [(&'static str, Check<F>); 13] = [
+        ("scalars", scalars),
+        ("nested", nested),
+        ("sequences", sequences),
+        ("arrays", arrays),
+        ("tuples", tuples),
+        ("newtypes", newtypes),
+        ("keys", keys),
+        ("options", options),
+        ("enums", enums),
+        ("keyed_enums", keyed_enums),
+        ("errors", errors),
+        ("errors_in_maps", errors_in_maps),
+        ("errors_in_sequences", errors_in_sequences),
+    ];
+
+    checks
+        .iter()
+        .filter_map(|(check, run)| {
+            let result =
+                panic::catch_unwind(panic::AssertUnwindSafe(|| run(format)))
+                    .unwrap_or_else(|_| Err("the check panicked".to_string()));
+
+            result.err().map(|message| Failure { check, message })
+        })
+        .collect()
+}
+
+type Check<F> = fn(&F) -> Result<(), String>;
+
+/// Run every check, panicking with a list of failures if any didn't pass.
+pub fn assert_conformance<F: Format>(format: &F) {
+    let failures = run(format);
+
+    if !failures.is_empty() {
+        let failures: Vec<_> =
+            failures.iter().map(|f| format!("  {}", f)).collect();
+        panic!("conformance checks failed:\n{}", failures.join("\n"));
+    }
+}
+
+fn parse<F: Format, T: DeserializeOwned>(
+    format: &F,
+    doc: &Doc,
+) -> Result<(String, T), String> {
+    let src = format.render(doc);
+
+    match format.deserialize(&src) {
+        Ok(value) => Ok((src, value)),
+        Err(e) => Err(format!("unable to deserialize {:?}: {}", src, e)),
+    }
+}
+
+/// Make sure a scalar's span covers its text, allowing for quotes.
+fn covers<T>(
+    src: &str,
+    spanned: &Spanned<T>,
+    expected: &str,
+) -> Result<(), String> {
+    let (start, end) = spanned.span();
+
+    match src.get(start..end) {
+        Some(text)
+            if text.contains(expected) && text.len() <= expected.len() + 2 =>
+        {
+            Ok(())
+        },
+        Some(text) => Err(format!(
+            "the span {}..{} covers {:?} instead of {:?}",
+            start, end, text, expected
+        )),
+        None => Err(format!(
+            "the span {}..{} isn't inside the document ({} bytes)",
+            start,
+            end,
+            src.len()
+        )),
+    }
+}
+
+fn contains<P, C>(
+    parent: &Spanned<P>,
+    child: &Spanned<C>,
+) -> Result<(), String> {
+    if parent.start() <= child.start() && child.end() <= parent.end() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the span {}..{} isn't inside its parent's span {}..{}",
+            child.start(),
+            child.end(),
+            parent.start(),
+            parent.end()
+        ))
+    }
+}
+
+fn scalars<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Scalars {
+        integer: Spanned<i64>,
+        string: Spanned<String>,
+        boolean: Spanned<bool>,
+        character: Spanned<char>,
+    }
+
+    let doc = Doc::Map(vec![
+        ("integer", Doc::Integer(42)),
+        ("string", Doc::String("hello")),
+        ("boolean", Doc::Bool(true)),
+        ("character", Doc::String("x")),
+    ]);
+    let (src, value): (_, Scalars) = parse(format, &doc)?;
+
+    covers(&src, &value.integer, "42")?;
+    covers(&src, &value.string, "hello")?;
+    covers(&src, &value.boolean, "true")?;
+    covers(&src, &value.character, "x")
+}
+
+fn nested<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Outer {
+        outer: Spanned<Inner>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Inner {
+        inner: Spanned<i64>,
+    }
+
+    let doc =
+        Doc::Map(vec![("outer", Doc::Map(vec![("inner", Doc::Integer(7))]))]);
+    let (src, value): (_, Outer) = parse(format, &doc)?;
+    let inner = &value.outer.value().inner;
+
+    covers(&src, inner, "7")?;
+    contains(&value.outer, inner)
+}
+
+fn sequences<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Sequence {
+        list: Spanned<Vec<Spanned<i64>>>,
+    }
+
+    let doc = Doc::Map(vec![(
+        "list",
+        Doc::Seq(vec![Doc::Integer(1), Doc::Integer(2), Doc::Integer(3)]),
+    )]);
+    let (src, value): (_, Sequence) = parse(format, &doc)?;
+    let items = value.list.value();
+
+    if items.len() != 3 {
+        return Err(format!("expected 3 items but found {}", items.len()));
+    }
+
+    for (item, expected) in items.iter().zip(&["1", "2", "3"]) {
+        covers(&src, item, expected)?;
+        contains(&value.list, item)?;
+    }
+
+    if items.windows(2).any(|pair| pair[0].end() > pair[1].start()) {
+        return Err("the items' spans are out of order".to_string());
+    }
+
+    Ok(())
+}
+
+fn arrays<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Arrays {
+        whole: Spanned<[i64; 2]>,
+        items: [Spanned<i64>; 2],
+    }
+
+    let doc = Doc::Map(vec![
+        ("whole", Doc::Seq(vec![Doc::Integer(1), Doc::Integer(2)])),
+        ("items", Doc::Seq(vec![Doc::Integer(3), Doc::Integer(4)])),
+    ]);
+    let (src, value): (_, Arrays) = parse(format, &doc)?;
+
+    if value.whole.value() != &[1, 2] {
+        return Err(format!("deserialized {:?}", value.whole.value()));
+    }
+    sequence_literal(&src, &value.whole, &["1", "2"])?;
+    covers(&src, &value.items[0], "3")?;
+    covers(&src, &value.items[1], "4")
+}
+
+fn tuples<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Point(i64, i64);
+
+    #[derive(serde::Deserialize)]
+    struct Tuples {
+        tuple: Spanned<(i64, i64)>,
+        tuple_struct: Spanned<Point>,
+        items: (Spanned<i64>, Spanned<i64>),
+    }
+
+    let doc = Doc::Map(vec![
+        ("tuple", Doc::Seq(vec![Doc::Integer(1), Doc::Integer(2)])),
+        (
+            "tuple_struct",
+            Doc::Seq(vec![Doc::Integer(3), Doc::Integer(4)]),
+        ),
+        ("items", Doc::Seq(vec![Doc::Integer(5), Doc::Integer(6)])),
+    ]);
+    let (src, value): (_, Tuples) = parse(format, &doc)?;
+    let Point(x, y) = value.tuple_struct.value();
+
+    if *value.tuple.value() != (1, 2) || (*x, *y) != (3, 4) {
+        return Err("deserialized the wrong values".to_string());
+    }
+    sequence_literal(&src, &value.tuple, &["1", "2"])?;
+    sequence_literal(&src, &value.tuple_struct, &["3", "4"])?;
+    covers(&src, &value.items.0, "5")?;
+    covers(&src, &value.items.1, "6")
+}
+
+fn newtypes<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Port(i64);
+
+    #[derive(serde::Deserialize)]
+    struct Host(Spanned<String>);
+
+    #[derive(serde::Deserialize)]
+    struct Newtypes {
+        port: Spanned<Port>,
+        host: Host,
+    }
+
+    let doc = Doc::Map(vec![
+        ("port", Doc::Integer(8080)),
+        ("host", Doc::String("localhost")),
+    ]);
+    let (src, value): (_, Newtypes) = parse(format, &doc)?;
+
+    if value.port.value().0 != 8080 {
+        return Err("deserialized the wrong port".to_string());
+    }
+    covers(&src, &value.port, "8080")?;
+    covers(&src, &value.host.0, "localhost")
+}
+
+/// Make sure a sequence's span covers the whole literal, from its first
+/// item to its last.
+fn sequence_literal<T>(
+    src: &str,
+    spanned: &Spanned<T>,
+    items: &[&str],
+) -> Result<(), String> {
+    let (start, end) = spanned.span();
+    let text = src.get(start..end).ok_or_else(|| {
+        format!("the span {}..{} isn't inside the document", start, end)
+    })?;
+
+    let mut rest = text;
+    for item in items {
+        match rest.find(item) {
+            Some(ix) => rest = &rest[ix + item.len()..],
+            None => {
+                return Err(format!(
+                    "the span {}..{} covers {:?}, which doesn't contain {:?}",
+                    start, end, text, items
+                ))
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn keys<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Keys {
+        map: BTreeMap<Spanned<String>, i64>,
+    }
+
+    let doc = Doc::Map(vec![(
+        "map",
+        Doc::Map(vec![
+            ("first", Doc::Integer(1)),
+            ("second", Doc::Integer(2)),
+        ]),
+    )]);
+    let (src, value): (_, Keys) = parse(format, &doc)?;
+
+    for key in value.map.keys() {
+        covers(&src, key, key.value())?;
+    }
+
+    Ok(())
+}
+
+fn options<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Options {
+        present: Option<Spanned<i64>>,
+        missing: Option<Spanned<i64>>,
+    }
+
+    let doc = Doc::Map(vec![("present", Doc::Integer(5))]);
+    let (src, value): (_, Options) = parse(format, &doc)?;
+
+    match value.present {
+        Some(present) => covers(&src, &present, "5")?,
+        None => return Err("the present field was None".to_string()),
+    }
+
+    match value.missing {
+        Some(_) => Err("the missing field was Some".to_string()),
+        None => Ok(()),
+    }
+}
+
+fn enums<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+        Slow,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Enums {
+        mode: Spanned<Mode>,
+    }
+
+    let doc = Doc::Map(vec![("mode", Doc::String("slow"))]);
+    let (src, value): (_, Enums) = parse(format, &doc)?;
+
+    match value.mode.value() {
+        Mode::Slow => covers(&src, &value.mode, "slow"),
+        Mode::Fast => Err("deserialized the wrong variant".to_string()),
+    }
+}
+
+/// A map of spanned keys to externally tagged enums, the shape of a typical
+/// linter config.
+fn keyed_enums<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Rule {
+        Allow,
+        MaxWidth { limit: Spanned<i64> },
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Rules {
+        rules: BTreeMap<Spanned<String>, Spanned<Rule>>,
+    }
+
+    let doc = Doc::Map(vec![(
+        "rules",
+        Doc::Map(vec![
+            (
+                "line_length",
+                Doc::Map(vec![(
+                    "max_width",
+                    Doc::Map(vec![("limit", Doc::Integer(100))]),
+                )]),
+            ),
+            ("todo", Doc::String("allow")),
+        ]),
+    )]);
+    let (src, value): (_, Rules) = parse(format, &doc)?;
+
+    if value.rules.len() != 2 {
+        return Err(format!(
+            "expected 2 rules but found {}",
+            value.rules.len()
+        ));
+    }
+
+    for (key, rule) in &value.rules {
+        covers(&src, key, key.value())?;
+
+        match (key.value().as_str(), rule.value()) {
+            ("todo", Rule::Allow) => covers(&src, rule, "allow")?,
+            ("line_length", Rule::MaxWidth { limit }) => {
+                covers(&src, limit, "100")?;
+                contains(rule, limit)?;
+            },
+            (name, _) => {
+                return Err(format!("deserialized the wrong rule for {}", name))
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Deserialize a document which should fail, making sure the error points
+/// at the literal `bad` (including any quotes around it).
+fn error_at<F: Format, T: DeserializeOwned>(
+    format: &F,
+    doc: &Doc,
+    bad: &str,
+) -> Result<(), String> {
+    let src = format.render(doc);
+
+    let error = match format.deserialize::<T>(&src) {
+        Ok(_) => {
+            return Err(format!("{:?} deserialized without an error", src))
+        },
+        Err(e) => e,
+    };
+
+    let start = src.find(bad).expect("the bad literal was rendered");
+    let end = start + bad.len();
+    let expected = start.saturating_sub(1)..=end + 1;
+
+    match format.error_offset(&src, &error) {
+        Some(offset) if expected.contains(&offset) => Ok(()),
+        Some(offset) => Err(format!(
+            "the error \"{}\" points at {} instead of {:?} at {}..{}",
+            error, offset, bad, start, end
+        )),
+        None => Err(format!("the error \"{}\" doesn't say where it is", error)),
+    }
+}
+
+fn errors<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(Debug, serde::Deserialize)]
+    struct Typed {
+        #[allow(dead_code)]
+        integer: Spanned<i64>,
+    }
+
+    let doc = Doc::Map(vec![("integer", Doc::String("oops"))]);
+
+    error_at::<F, Typed>(format, &doc, "oops")
+}
+
+/// An error from a `Spanned` value inside a map inside a `Spanned` value.
+fn errors_in_maps<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(Debug, serde::Deserialize)]
+    struct Outer {
+        #[allow(dead_code)]
+        outer: Spanned<BTreeMap<String, Spanned<i64>>>,
+    }
+
+    let doc = Doc::Map(vec![(
+        "outer",
+        Doc::Map(vec![
+            ("fine", Doc::Integer(1)),
+            ("broken", Doc::String("oops")),
+        ]),
+    )]);
+
+    error_at::<F, Outer>(format, &doc, "oops")
+}
+
+/// An error from a `Spanned` value inside a sequence inside a `Spanned`
+/// value.
+fn errors_in_sequences<F: Format>(format: &F) -> Result<(), String> {
+    #[derive(Debug, serde::Deserialize)]
+    struct Outer {
+        #[allow(dead_code)]
+        list: Spanned<Vec<Spanned<i64>>>,
+    }
+
+    let doc = Doc::Map(vec![(
+        "list",
+        Doc::Seq(vec![Doc::Integer(1), Doc::String("oops"), Doc::Integer(3)]),
+    )]);
+
+    error_at::<F, Outer>(format, &doc, "oops")
+}
@@ -0,0 +1,834 @@
+use core::{
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+};
+use serde::{
+    ser::{
+        SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+        SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+    },
+    Serialize, Serializer,
+};
+
+/// Hash a value's contents and structure, ignoring any spans.
+///
+/// [`Spanned`] serializes transparently, so this feeds the value's
+/// serialized form into `state`. Two values which only differ in where they
+/// came from (e.g. after reformatting a config file) hash the same, which is
+/// what incremental computation frameworks want.
+///
+/// The entries of a map are combined so their order doesn't matter, which
+/// means equal `HashMap`s always hash the same. Sequences are hashed in
+/// order, so use a `BTreeSet` rather than a `HashSet`. An integer hashes the
+/// same regardless of the type used to store it.
+///
+/// Types which don't implement `Serialize` can use [`ContentHash`] instead.
+///
+/// ```rust
+/// use serde_spanned::{content_hash, Spanned};
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::Hasher;
+///
+/// fn hash(value: &Spanned<&str>) -> u64 {
+///     let mut hasher = DefaultHasher::new();
+///     content_hash(value, &mut hasher).unwrap();
+///     hasher.finish()
+/// }
+///
+/// assert_eq!(
+///     hash(&Spanned::new("localhost", 7, 18)),
+///     hash(&Spanned::new("localhost", 42, 53)),
+/// );
+/// ```
+///
+/// [`Spanned`]: crate::Spanned
+pub fn content_hash<T, H>(
+    value: &T,
+    state: &mut H,
+) -> Result<(), ContentHashError>
+where
+    T: Serialize + ?Sized,
+    H: Hasher,
+{
+    value.serialize(&mut ContentHasher { state })
+}
+
+/// Hashing a value's contents and structure while ignoring spans, without
+/// going through `Serialize`.
+///
+/// This can be derived with `#[derive(ContentHash)]` when the `derive`
+/// feature is enabled. Like [`WalkSpans`](crate::WalkSpans), fields marked
+/// with `#[walk_spans(skip)]` or `#[serde(skip)]` are left out. The
+/// entries of maps and sets are combined so their order doesn't matter.
+///
+/// ```rust
+/// use serde_spanned::{ContentHash, Spanned};
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::Hasher;
+///
+/// fn hash(value: &[Spanned<u16>]) -> u64 {
+///     let mut hasher = DefaultHasher::new();
+///     value.content_hash(&mut hasher);
+///     hasher.finish()
+/// }
+///
+/// let first = [Spanned::new(8080, 7, 11)];
+/// let second = [Spanned::new(8080, 20, 24)];
+///
+/// assert_eq!(hash(&first), hash(&second));
+/// ```
+pub trait ContentHash {
+    fn content_hash(&self, state: &mut dyn Hasher);
+}
+
+/// The error returned by [`content_hash()`] when a value's [`Serialize`]
+/// implementation fails.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ContentHashError;
+
+impl Display for ContentHashError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to serialize the value")
+    }
+}
+
+impl serde::ser::StdError for ContentHashError {}
+
+impl serde::ser::Error for ContentHashError {
+    fn custom<T: Display>(_msg: T) -> Self { ContentHashError }
+}
+
+/// Tags written before each item so values with different shapes (e.g.
+/// `Some(())` and `[()]`) don't hash the same.
+#[derive(Copy, Clone, Hash)]
+enum Tag {
+    Bool,
+    Signed,
+    Unsigned,
+    Float,
+    Char,
+    Str,
+    Bytes,
+    None,
+    Some,
+    Unit,
+    UnitStruct,
+    UnitVariant,
+    NewtypeStruct,
+    NewtypeVariant,
+    Seq,
+    Tuple,
+    TupleStruct,
+    TupleVariant,
+    Map,
+    #[cfg(feature = "alloc")]
+    Set,
+    Struct,
+    StructVariant,
+    Element,
+    Skipped,
+    End,
+}
+
+struct ContentHasher<'a, H> {
+    state: &'a mut H,
+}
+
+impl<H: Hasher> ContentHasher<'_, H> {
+    fn write<T: Hash + ?Sized>(&mut self, tag: Tag, value: &T) {
+        tag.hash(self.state);
+        value.hash(self.state);
+    }
+
+    fn element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), ContentHashError> {
+        Tag::Element.hash(self.state);
+        value.serialize(self)
+    }
+
+    fn end(&mut self) -> Result<(), ContentHashError> {
+        Tag::End.hash(self.state);
+        Ok(())
+    }
+}
+
+impl<'b, 'a, H: Hasher> Serializer for &'b mut ContentHasher<'a, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+    type SerializeMap = MapHasher<'b, H>;
+    type SerializeSeq = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Self::Error> {
+        self.write(Tag::Bool, &v);
+        Ok(())
+    }
+
+    // Integers are widened, and non-negative signed integers are hashed as
+    // unsigned, so the same number hashes the same regardless of which type
+    // was used to store it
+    fn serialize_i8(self, v: i8) -> Result<(), Self::Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Self::Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Self::Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Self::Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Self::Error> {
+        if v >= 0 {
+            return self.serialize_u128(v as u128);
+        }
+
+        self.write(Tag::Signed, &v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Self::Error> {
+        self.serialize_u128(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Self::Error> {
+        self.serialize_u128(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Self::Error> {
+        self.serialize_u128(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Self::Error> {
+        self.serialize_u128(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Self::Error> {
+        self.write(Tag::Unsigned, &v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Self::Error> {
+        self.write(Tag::Float, &v.to_bits());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Self::Error> {
+        self.write(Tag::Char, &v);
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Self::Error> {
+        self.write(Tag::Str, v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Self::Error> {
+        self.write(Tag::Bytes, v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Self::Error> {
+        Tag::None.hash(self.state);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        Tag::Some.hash(self.state);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Self::Error> {
+        Tag::Unit.hash(self.state);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(
+        self,
+        name: &'static str,
+    ) -> Result<(), Self::Error> {
+        self.write(Tag::UnitStruct, name);
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Self::Error> {
+        self.write(Tag::UnitVariant, &(name, variant));
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.write(Tag::NewtypeStruct, name);
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.write(Tag::NewtypeVariant, &(name, variant));
+        value.serialize(self)
+    }
+
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, Self::Error> {
+        Tag::Seq.hash(self.state);
+        Ok(self)
+    }
+
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<Self::SerializeTuple, Self::Error> {
+        Tag::Tuple.hash(self.state);
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.write(Tag::TupleStruct, name);
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.write(Tag::TupleVariant, &(name, variant));
+        Ok(self)
+    }
+
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapHasher {
+            state: &mut *self.state,
+            entries: Unordered::default(),
+            entry: Fnv::default(),
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.write(Tag::Struct, name);
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.write(Tag::StructVariant, &(name, variant));
+        Ok(self)
+    }
+
+    fn collect_str<T: Display + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        // Hash the formatted text without needing to allocate a String
+        struct Writer<'a, H>(&'a mut H);
+
+        impl<H: Hasher> fmt::Write for Writer<'_, H> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0.write(s.as_bytes());
+                Ok(())
+            }
+        }
+
+        Tag::Str.hash(self.state);
+        fmt::write(&mut Writer(self.state), format_args!("{}", value))
+            .map_err(|_| ContentHashError)?;
+        // Match the terminator `str`'s Hash impl uses
+        self.state.write_u8(0xff);
+        Ok(())
+    }
+
+    fn is_human_readable(&self) -> bool { false }
+}
+
+impl<H: Hasher> SerializeSeq for &mut ContentHasher<'_, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Self::Error> { ContentHasher::end(self) }
+}
+
+impl<H: Hasher> SerializeTuple for &mut ContentHasher<'_, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Self::Error> { ContentHasher::end(self) }
+}
+
+impl<H: Hasher> SerializeTupleStruct for &mut ContentHasher<'_, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Self::Error> { ContentHasher::end(self) }
+}
+
+impl<H: Hasher> SerializeTupleVariant for &mut ContentHasher<'_, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Self::Error> { ContentHasher::end(self) }
+}
+
+/// Hashes each entry of a map on its own, then combines them so the order
+/// they were serialized in doesn't matter.
+struct MapHasher<'a, H> {
+    state: &'a mut H,
+    entries: Unordered,
+    entry: Fnv,
+}
+
+impl<H: Hasher> SerializeMap for MapHasher<'_, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+
+    fn serialize_key<T: Serialize + ?Sized>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), Self::Error> {
+        self.entry = Fnv::default();
+        key.serialize(&mut ContentHasher {
+            state: &mut self.entry,
+        })
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        value.serialize(&mut ContentHasher {
+            state: &mut self.entry,
+        })?;
+        self.entries.add(self.entry.finish());
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Self::Error> {
+        self.entries.finish(Tag::Map, self.state);
+        Ok(())
+    }
+}
+
+impl<H: Hasher> SerializeStruct for &mut ContentHasher<'_, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.write(Tag::Element, key);
+        value.serialize(&mut **self)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.write(Tag::Skipped, key);
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Self::Error> { ContentHasher::end(self) }
+}
+
+impl<H: Hasher> SerializeStructVariant for &mut ContentHasher<'_, H> {
+    type Error = ContentHashError;
+    type Ok = ();
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.write(Tag::Element, key);
+        value.serialize(&mut **self)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.write(Tag::Skipped, key);
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Self::Error> { ContentHasher::end(self) }
+}
+
+/// Combines the hashes of a collection's entries so the order they were
+/// visited in doesn't matter.
+#[derive(Default)]
+struct Unordered {
+    sum: u64,
+    len: u64,
+}
+
+impl Unordered {
+    fn add(&mut self, hash: u64) {
+        // Mix the bits first (the finalizer from MurmurHash3), so summing
+        // similar hashes doesn't cancel them out
+        let mut hash = hash;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^= hash >> 33;
+
+        self.sum = self.sum.wrapping_add(hash);
+        self.len += 1;
+    }
+
+    #[cfg(feature = "alloc")]
+    fn add_with(&mut self, hash_entry: impl FnOnce(&mut dyn Hasher)) {
+        let mut entry = Fnv::default();
+        hash_entry(&mut entry);
+        self.add(entry.finish());
+    }
+
+    fn finish<H: Hasher + ?Sized>(self, tag: Tag, mut state: &mut H) {
+        tag.hash(&mut state);
+        state.write_u64(self.len);
+        state.write_u64(self.sum);
+    }
+}
+
+/// The 64-bit FNV-1a hash, used for the entries of maps and sets because
+/// the caller's `Hasher` can't be cloned or reset.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self { Fnv(0xcbf2_9ce4_8422_2325) }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 { self.0 }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+macro_rules! leaves {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl ContentHash for $ty {
+                fn content_hash(&self, mut state: &mut dyn Hasher) {
+                    // serializing a leaf can't fail
+                    let _ = $crate::content_hash(self, &mut state);
+                }
+            }
+        )*
+    };
+}
+
+leaves!(
+    bool,
+    char,
+    str,
+    (),
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+);
+
+impl<T: ContentHash + ?Sized> ContentHash for &T {
+    fn content_hash(&self, state: &mut dyn Hasher) {
+        (**self).content_hash(state);
+    }
+}
+
+impl<T: ContentHash> ContentHash for crate::Spanned<T> {
+    fn content_hash(&self, state: &mut dyn Hasher) {
+        self.value().content_hash(state);
+    }
+}
+
+impl<T: ContentHash> ContentHash for crate::Secret<T> {
+    fn content_hash(&self, state: &mut dyn Hasher) {
+        self.expose().content_hash(state);
+    }
+}
+
+impl<T: ContentHash> ContentHash for Option<T> {
+    fn content_hash(&self, mut state: &mut dyn Hasher) {
+        match self {
+            Some(value) => {
+                Tag::Some.hash(&mut state);
+                value.content_hash(state);
+            },
+            None => Tag::None.hash(&mut state),
+        }
+    }
+}
+
+impl<T: ContentHash> ContentHash for [T] {
+    fn content_hash(&self, state: &mut dyn Hasher) { hash_seq(state, self); }
+}
+
+fn hash_seq<I>(mut state: &mut dyn Hasher, items: I)
+where
+    I: IntoIterator,
+    I::Item: ContentHash,
+{
+    Tag::Seq.hash(&mut state);
+
+    for item in items {
+        Tag::Element.hash(&mut state);
+        item.content_hash(state);
+    }
+
+    Tag::End.hash(&mut state);
+}
+
+#[cfg(feature = "alloc")]
+fn hash_map<'a, K, V, I>(state: &mut dyn Hasher, entries: I)
+where
+    K: ContentHash + 'a,
+    V: ContentHash + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    let mut combined = Unordered::default();
+
+    for (key, value) in entries {
+        combined.add_with(|entry| {
+            key.content_hash(entry);
+            value.content_hash(entry);
+        });
+    }
+
+    combined.finish(Tag::Map, state);
+}
+
+#[cfg(feature = "alloc")]
+fn hash_set<'a, T, I>(state: &mut dyn Hasher, items: I)
+where
+    T: ContentHash + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let mut combined = Unordered::default();
+
+    for item in items {
+        combined.add_with(|entry| item.content_hash(entry));
+    }
+
+    combined.finish(Tag::Set, state);
+}
+
+/// Used by `#[derive(ContentHash)]` to hash a struct or enum variant's
+/// fields.
+#[doc(hidden)]
+pub struct __StructHasher<'a> {
+    state: &'a mut dyn Hasher,
+}
+
+impl<'a> __StructHasher<'a> {
+    pub fn new(
+        mut state: &'a mut dyn Hasher,
+        name: &str,
+        variant: Option<&str>,
+    ) -> Self {
+        match variant {
+            Some(variant) => {
+                Tag::StructVariant.hash(&mut state);
+                (name, variant).hash(&mut state);
+            },
+            None => {
+                Tag::Struct.hash(&mut state);
+                name.hash(&mut state);
+            },
+        }
+
+        __StructHasher { state }
+    }
+
+    pub fn field<T: ContentHash + ?Sized>(&mut self, name: &str, value: &T) {
+        Tag::Element.hash(&mut self.state);
+        name.hash(&mut self.state);
+        value.content_hash(self.state);
+    }
+
+    pub fn end(mut self) { Tag::End.hash(&mut self.state); }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc_impls {
+    use super::{hash_map, hash_seq, hash_set, ContentHash, Tag};
+    use crate::{value::Number, SpannedValue};
+    use alloc::{
+        borrow::Cow,
+        boxed::Box,
+        collections::{BTreeMap, BTreeSet, VecDeque},
+        string::String,
+        vec::Vec,
+    };
+    use core::hash::{Hash, Hasher};
+
+    leaves!(String);
+
+    impl ContentHash for Cow<'_, str> {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            (**self).content_hash(state);
+        }
+    }
+
+    impl<T: ContentHash + ?Sized> ContentHash for Box<T> {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            (**self).content_hash(state);
+        }
+    }
+
+    impl<T: ContentHash> ContentHash for Vec<T> {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            hash_seq(state, self);
+        }
+    }
+
+    impl<T: ContentHash> ContentHash for VecDeque<T> {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            hash_seq(state, self);
+        }
+    }
+
+    impl<K: ContentHash, V: ContentHash> ContentHash for BTreeMap<K, V> {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            hash_map(state, self);
+        }
+    }
+
+    impl<T: ContentHash> ContentHash for BTreeSet<T> {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            hash_set(state, self);
+        }
+    }
+
+    impl ContentHash for SpannedValue {
+        fn content_hash(&self, mut state: &mut dyn Hasher) {
+            match self {
+                SpannedValue::Null => Tag::Unit.hash(&mut state),
+                SpannedValue::Bool(b) => b.content_hash(state),
+                SpannedValue::Number(Number::Unsigned(n)) => {
+                    n.content_hash(state)
+                },
+                SpannedValue::Number(Number::Signed(n)) => {
+                    n.content_hash(state)
+                },
+                SpannedValue::Number(Number::Float(n)) => n.content_hash(state),
+                SpannedValue::String(s) => s.content_hash(state),
+                SpannedValue::Array(items) => items.content_hash(state),
+                SpannedValue::Object(entries) => {
+                    hash_map(state, entries.iter().map(|(k, v)| (k, v)))
+                },
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod std_impls {
+    use super::{hash_map, hash_set, ContentHash};
+    use core::hash::Hasher;
+    use std::collections::{HashMap, HashSet};
+
+    impl<K, V, S> ContentHash for HashMap<K, V, S>
+    where
+        K: ContentHash,
+        V: ContentHash,
+    {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            hash_map(state, self);
+        }
+    }
+
+    impl<T: ContentHash, S> ContentHash for HashSet<T, S> {
+        fn content_hash(&self, state: &mut dyn Hasher) {
+            hash_set(state, self);
+        }
+    }
+}
@@ -0,0 +1,599 @@
+//! Helpers for `Deserializer` implementations which want to support
+//! [`Spanned`].
+//!
+//! When [`Spanned<T>`] is deserialized it calls
+//! [`Deserializer::deserialize_struct()`] with the well-known [`NAME`] and
+//! [`FIELDS`]. A span-aware deserializer should check for this with
+//! [`is_spanned()`] and pass one of the [`MapAccess`] implementations from
+//! this module to [`Visitor::visit_map()`].
+//!
+//! ```rust,ignore
+//! fn deserialize_struct<V>(
+//!     self,
+//!     name: &'static str,
+//!     fields: &'static [&'static str],
+//!     visitor: V,
+//! ) -> Result<V::Value, Error>
+//! where
+//!     V: Visitor<'de>,
+//! {
+//!     if serde_spanned::de::is_spanned(name, fields) {
+//!         let (start, end) = self.current_span();
+//!         return visitor.visit_map(SpannedDeserializer::new(start, end, self));
+//!     }
+//!
+//!     self.deserialize_any(visitor)
+//! }
+//! ```
+//!
+//! The integration tests (`tests/common/mod.rs`) contain a complete
+//! deserializer using each of them.
+//!
+//! [`Spanned`]: crate::Spanned
+//! [`Spanned<T>`]: crate::Spanned
+//! [`NAME`]: crate::NAME
+//! [`FIELDS`]: crate::FIELDS
+//! [`Visitor::visit_map()`]: serde::de::Visitor::visit_map
+
+use crate::{END, FIELDS, NAME, START, VALUE};
+use core::{fmt, marker::PhantomData};
+use serde::de::{
+    DeserializeSeed, Deserializer, EnumAccess, Error, IntoDeserializer,
+    MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+
+/// Is a call to [`Deserializer::deserialize_struct()`] asking for a
+/// [`Spanned`](crate::Spanned) value?
+pub fn is_spanned(name: &str, fields: &[&str]) -> bool {
+    name == NAME && fields == FIELDS
+}
+
+/// A [`MapAccess`] for deserializing a [`Spanned`](crate::Spanned) value
+/// when its span is already known.
+///
+/// This is the easiest option for formats which parse the whole document
+/// into a tree before deserializing it.
+#[derive(Debug, Clone)]
+pub struct SpannedDeserializer<D> {
+    start: Option<usize>,
+    end: Option<usize>,
+    value: Option<D>,
+}
+
+impl<D> SpannedDeserializer<D> {
+    pub const fn new(start: usize, end: usize, value: D) -> Self {
+        SpannedDeserializer {
+            start: Some(start),
+            end: Some(end),
+            value: Some(value),
+        }
+    }
+}
+
+impl<'de, D> MapAccess<'de> for SpannedDeserializer<D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    fn next_key_seed<K>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let key = if self.start.is_some() {
+            START
+        } else if self.end.is_some() {
+            END
+        } else if self.value.is_some() {
+            VALUE
+        } else {
+            return Ok(None);
+        };
+
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        if let Some(start) = self.start.take() {
+            seed.deserialize(start.into_deserializer())
+        } else if let Some(end) = self.end.take() {
+            seed.deserialize(end.into_deserializer())
+        } else if let Some(value) = self.value.take() {
+            seed.deserialize(value)
+        } else {
+            Err(Error::custom("next_value_seed called after the last value"))
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(
+            self.start.is_some() as usize
+                + self.end.is_some() as usize
+                + self.value.is_some() as usize,
+        )
+    }
+}
+
+/// Something which can report its position in the source text.
+///
+/// This is implemented by the deserializers of streaming formats so
+/// [`StreamingSpanned`] can record where a value starts and ends.
+pub trait Offset {
+    /// The offset of the next value, after skipping any leading whitespace,
+    /// comments, or other trivia.
+    fn start_offset(&mut self) -> usize;
+
+    /// The offset immediately after the most recently deserialized value.
+    fn end_offset(&self) -> usize;
+}
+
+impl<O: Offset + ?Sized> Offset for &mut O {
+    fn start_offset(&mut self) -> usize { (**self).start_offset() }
+
+    fn end_offset(&self) -> usize { (**self).end_offset() }
+}
+
+/// A [`BufRead`] which keeps track of how many bytes have been read from it,
+/// so a streaming deserializer can report absolute offsets without holding
+/// the whole input in memory.
+///
+/// Only the wrapped reader's buffer is kept, so memory use doesn't grow with
+/// the size of the input. A deserializer reading from a `CountingBufReader`
+/// can forward its [`Offset`] implementation to the reader, as long as it
+/// doesn't read ahead of the value it is deserializing (peek with
+/// [`BufRead::fill_buf()`] instead).
+///
+/// ```rust
+/// use serde_spanned::de::{CountingBufReader, Offset};
+/// use std::io::BufRead;
+///
+/// let mut reader = CountingBufReader::new("  {}\n  []\n".as_bytes());
+///
+/// assert_eq!(reader.start_offset(), 2);
+/// reader.consume(2);
+/// assert_eq!(reader.end_offset(), 4);
+/// assert_eq!(reader.start_offset(), 7);
+/// ```
+///
+/// [`BufRead`]: std::io::BufRead
+/// [`BufRead::fill_buf()`]: std::io::BufRead::fill_buf
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct CountingBufReader<R> {
+    inner: R,
+    offset: usize,
+}
+
+#[cfg(feature = "std")]
+impl<R> CountingBufReader<R> {
+    pub const fn new(inner: R) -> Self { CountingBufReader::at(inner, 0) }
+
+    /// Start counting from `offset`, e.g. when `inner` has already been
+    /// partially read.
+    pub const fn at(inner: R, offset: usize) -> Self {
+        CountingBufReader { inner, offset }
+    }
+
+    /// The number of bytes read so far.
+    pub const fn offset(&self) -> usize { self.offset }
+
+    pub const fn get_ref(&self) -> &R { &self.inner }
+
+    pub fn get_mut(&mut self) -> &mut R { &mut self.inner }
+
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::BufRead> std::io::Read for CountingBufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.offset += bytes_read;
+        Ok(bytes_read)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::BufRead> std::io::BufRead for CountingBufReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> { self.inner.fill_buf() }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.offset += amt;
+    }
+}
+
+/// Skips leading ASCII whitespace. Any IO error stops the skipping early and
+/// is left for the deserializer to run into on its next read.
+#[cfg(feature = "std")]
+impl<R: std::io::BufRead> Offset for CountingBufReader<R> {
+    fn start_offset(&mut self) -> usize {
+        use std::io::{BufRead, ErrorKind};
+
+        loop {
+            let (whitespace, done) = match self.inner.fill_buf() {
+                Ok(buf) => {
+                    let n = buf
+                        .iter()
+                        .take_while(|b| b.is_ascii_whitespace())
+                        .count();
+                    (n, n < buf.len() || buf.is_empty())
+                },
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+
+            self.consume(whitespace);
+
+            if done {
+                break;
+            }
+        }
+
+        self.offset
+    }
+
+    fn end_offset(&self) -> usize { self.offset }
+}
+
+/// A [`MapAccess`] for deserializing a [`Spanned`](crate::Spanned) value
+/// from a streaming deserializer, capturing the span as the value is read.
+///
+/// The keys are yielded as `start`, `value`, then `end`, so the end offset
+/// can be read after the value has been deserialized. This isn't the order
+/// [`SpannedDeserializer`] uses: this crate's [`Spanned`](crate::Spanned)
+/// accepts either, but visitors which expect the keys in protocol order
+/// (e.g. `toml::Spanned`) will fail with a "spanned end key not found"
+/// error. Use [`SpannedDeserializer`] when those need to be supported.
+#[derive(Debug)]
+pub struct StreamingSpanned<'a, R, E> {
+    de: &'a mut R,
+    state: State,
+    _error: PhantomData<fn() -> E>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum State {
+    Start,
+    Value,
+    End,
+    Done,
+}
+
+impl<'a, R, E> StreamingSpanned<'a, R, E> {
+    pub fn new(de: &'a mut R) -> Self {
+        StreamingSpanned {
+            de,
+            state: State::Start,
+            _error: PhantomData,
+        }
+    }
+}
+
+impl<'de, 'a, R, E> MapAccess<'de> for StreamingSpanned<'a, R, E>
+where
+    R: Offset,
+    for<'b> &'b mut R: Deserializer<'de, Error = E>,
+    E: Error,
+{
+    type Error = E;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, E>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let key = match self.state {
+            State::Start => START,
+            State::Value => VALUE,
+            State::End => END,
+            State::Done => return Ok(None),
+        };
+
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, E>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.state {
+            State::Start => {
+                self.state = State::Value;
+                let start = self.de.start_offset();
+                seed.deserialize(start.into_deserializer())
+            },
+            State::Value => {
+                self.state = State::End;
+                seed.deserialize(&mut *self.de)
+            },
+            State::End => {
+                self.state = State::Done;
+                let end = self.de.end_offset();
+                seed.deserialize(end.into_deserializer())
+            },
+            State::Done => Err(Error::custom(
+                "next_value_seed called after the last value",
+            )),
+        }
+    }
+}
+
+/// A [`Deserializer`] which forwards to another one, but says it is
+/// human-readable.
+///
+/// [`Spanned`](crate::Spanned) skips the span protocol for deserializers
+/// which aren't human-readable and uses an empty `0..0` span. Wrap the
+/// deserializer of a binary format which does record byte offsets in
+/// `HumanReadable` to ask for spans anyway. Every nested value is
+/// deserialized through the wrapper too.
+///
+/// Unlike [`BinarySpans`](crate::BinarySpans), this doesn't need a
+/// thread-local, so it works without the `std` feature.
+///
+/// ```rust,ignore
+/// let mut de = my_format::Deserializer::from_slice(&bytes);
+/// let value: Spanned<Config> =
+///     Spanned::deserialize(HumanReadable::new(&mut de))?;
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct HumanReadable<D>(D);
+
+impl<D> HumanReadable<D> {
+    pub const fn new(de: D) -> Self { HumanReadable(de) }
+
+    pub fn into_inner(self) -> D { self.0 }
+}
+
+/// Forward `deserialize_*()` methods to the wrapped deserializer, wrapping
+/// the visitor so nested values keep the override.
+macro_rules! forward_wrapped {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, D::Error> {
+                self.0.$method($($arg,)* Wrap(visitor))
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for HumanReadable<D> {
+    type Error = D::Error;
+
+    forward_wrapped! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(
+            name: &'static str,
+            fields: &'static [&'static str]
+        );
+        deserialize_enum(
+            name: &'static str,
+            variants: &'static [&'static str]
+        );
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn is_human_readable(&self) -> bool { true }
+}
+
+/// Wraps the visitors, seeds, and accessors a [`HumanReadable`] hands to the
+/// deserializer, so anything they deserialize is wrapped in turn.
+struct Wrap<T>(T);
+
+/// Forward `visit_*()` methods which take a plain value.
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method<E: Error>(self, v: $ty) -> Result<V::Value, E> {
+                self.0.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Wrap<V> {
+    type Value = V::Value;
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+    }
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn visit_string<E: Error>(
+        self,
+        v: alloc::string::String,
+    ) -> Result<V::Value, E> {
+        self.0.visit_string(v)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn visit_byte_buf<E: Error>(
+        self,
+        v: alloc::vec::Vec<u8>,
+    ) -> Result<V::Value, E> {
+        self.0.visit_byte_buf(v)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<V::Value, E> { self.0.visit_none() }
+
+    fn visit_unit<E: Error>(self) -> Result<V::Value, E> { self.0.visit_unit() }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<V::Value, D::Error> {
+        self.0.visit_some(HumanReadable(de))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<V::Value, D::Error> {
+        self.0.visit_newtype_struct(HumanReadable(de))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        seq: A,
+    ) -> Result<V::Value, A::Error> {
+        self.0.visit_seq(Wrap(seq))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        map: A,
+    ) -> Result<V::Value, A::Error> {
+        self.0.visit_map(Wrap(map))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(
+        self,
+        data: A,
+    ) -> Result<V::Value, A::Error> {
+        self.0.visit_enum(Wrap(data))
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Wrap<S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<S::Value, D::Error> {
+        self.0.deserialize(HumanReadable(de))
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error> {
+        self.0.next_element_seed(Wrap(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> { self.0.size_hint() }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        self.0.next_key_seed(Wrap(seed))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, A::Error> {
+        self.0.next_value_seed(Wrap(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> { self.0.size_hint() }
+}
+
+impl<'de, A: EnumAccess<'de>> EnumAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+    type Variant = Wrap<A::Variant>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), A::Error> {
+        self.0
+            .variant_seed(Wrap(seed))
+            .map(|(value, variant)| (value, Wrap(variant)))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> { self.0.unit_variant() }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, A::Error> {
+        self.0.newtype_variant_seed(Wrap(seed))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        self.0.tuple_variant(len, Wrap(visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        self.0.struct_variant(fields, Wrap(visitor))
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    snapshot::{Excerpt, Snapshot},
+    Position, Span, Spanned,
+};
+
+/// A deserialized value bundled with the source text it came from.
+///
+/// Keeping the value, its source, and the source's name together means
+/// any span inside the value can be rendered without passing the source
+/// around separately.
+///
+/// ```rust
+/// use serde_spanned::{Document, Spanned};
+///
+/// let src = "[server]\nport = 8080\n";
+/// let doc = Document::new(Spanned::new(8080_u16, 16, 20), src)
+///     .named("config.toml");
+///
+/// assert_eq!(doc.span().len(), src.len());
+/// assert_eq!(
+///     doc.excerpt(&doc.value).to_string(),
+///     r#""8080" (config.toml 2:8..2:12) -> 8080"#,
+/// );
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Document<'a, T> {
+    pub value: T,
+    pub src: &'a str,
+    pub name: Option<&'a str>,
+}
+
+impl<'a, T> Document<'a, T> {
+    pub const fn new(value: T, src: &'a str) -> Self {
+        Document {
+            value,
+            src,
+            name: None,
+        }
+    }
+
+    /// Attach the name of the source file.
+    pub fn named(self, name: &'a str) -> Self {
+        Document {
+            name: Some(name),
+            ..self
+        }
+    }
+
+    /// The span covering the whole document.
+    pub const fn span(&self) -> Span { Span::new(0, self.src.len()) }
+
+    /// The text covered by a span, if it is inside the document.
+    pub fn text(&self, span: Span) -> Option<&'a str> {
+        self.src.get(span.as_range())
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        Position::from_offset(self.src, offset)
+    }
+
+    /// Render a [`Spanned`] value from inside this document.
+    pub fn excerpt<'b, U>(&'b self, spanned: &'b Spanned<U>) -> Excerpt<'b, U> {
+        let excerpt = Excerpt::new(spanned, self.src);
+
+        match self.name {
+            Some(name) => excerpt.named(name),
+            None => excerpt,
+        }
+    }
+
+    /// Render every span inside the value.
+    pub fn snapshot(&self) -> Snapshot<'_, T> {
+        let snapshot = Snapshot::new(&self.value, self.src);
+
+        match self.name {
+            Some(name) => snapshot.named(name),
+            None => snapshot,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Document<'a, U> {
+        Document {
+            value: f(self.value),
+            src: self.src,
+            name: self.name,
+        }
+    }
+
+    pub fn into_value(self) -> T { self.value }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    walk::{Path, WalkSpans},
+    Span,
+};
+
+/// A change to the source text, where the bytes covered by `replaced` were
+/// replaced with `new_len` bytes of new text.
+///
+/// This is used to update spans from before the edit so the whole document
+/// doesn't need to be re-parsed on every keystroke.
+///
+/// ```rust
+/// use serde_spanned::{Adjusted, Span, TextEdit};
+///
+/// // port = 80 -> port = 8080
+/// let edit = TextEdit::new(Span::new(7, 9), 4);
+///
+/// assert_eq!(edit.adjust(Span::new(0, 4)), Adjusted::Clean(Span::new(0, 4)));
+/// assert_eq!(edit.adjust(Span::new(7, 9)), Adjusted::Dirty(Span::new(7, 11)));
+/// assert_eq!(edit.adjust(Span::new(10, 15)), Adjusted::Clean(Span::new(12, 17)));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    pub replaced: Span,
+    pub new_len: usize,
+}
+
+/// A span from before a [`TextEdit`], updated to point into the new text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Adjusted {
+    /// The edit didn't touch the text inside this span, although the span
+    /// may have moved.
+    Clean(Span),
+    /// The edit touched or overlapped this span, so the value needs to be
+    /// re-parsed. The span covers everything it grew or shrank to.
+    Dirty(Span),
+}
+
+impl Adjusted {
+    pub const fn span(self) -> Span {
+        match self {
+            Adjusted::Clean(span) | Adjusted::Dirty(span) => span,
+        }
+    }
+
+    pub const fn is_dirty(self) -> bool {
+        match self {
+            Adjusted::Clean(_) => false,
+            Adjusted::Dirty(_) => true,
+        }
+    }
+}
+
+impl TextEdit {
+    pub const fn new(replaced: Span, new_len: usize) -> Self {
+        TextEdit { replaced, new_len }
+    }
+
+    /// Where the new text ends up.
+    pub const fn inserted(self) -> Span {
+        Span::new(self.replaced.start(), self.replaced.start() + self.new_len)
+    }
+
+    /// Map an offset after the edit into the new text.
+    fn shift(self, offset: usize) -> usize {
+        offset - self.replaced.end() + self.inserted().end()
+    }
+
+    /// Update a span from before the edit.
+    ///
+    /// Spans which end exactly where the edit starts (or start where it
+    /// ends) are considered dirty, because typing at the end of a token
+    /// changes that token.
+    pub fn adjust(self, span: Span) -> Adjusted {
+        let edit = self.replaced;
+
+        if span.end() < edit.start() {
+            Adjusted::Clean(span)
+        } else if span.start() > edit.end() {
+            Adjusted::Clean(Span::new(
+                self.shift(span.start()),
+                self.shift(span.end()),
+            ))
+        } else {
+            let start = span.start().min(edit.start());
+            let end = if span.end() >= edit.end() {
+                self.shift(span.end())
+            } else {
+                self.inserted().end()
+            };
+
+            Adjusted::Dirty(Span::new(start, end.max(self.inserted().end())))
+        }
+    }
+
+    /// Find the smallest span in `value` which contains the edit, mapped
+    /// into the new text.
+    ///
+    /// This is the region that needs to be re-parsed. `None` means the edit
+    /// wasn't inside any of the value's spans, so the whole document should
+    /// be re-parsed.
+    pub fn dirty_region<W: WalkSpans + ?Sized>(
+        self,
+        value: &W,
+    ) -> Option<Span> {
+        let mut smallest: Option<Span> = None;
+
+        value.walk_spans(&Path::ROOT, &mut |_, span| {
+            let span = Span::from(span);
+
+            if span.contains_span(self.replaced)
+                && smallest.map_or(true, |s| span.len() <= s.len())
+            {
+                smallest = Some(span);
+            }
+        });
+
+        smallest.map(|span| self.adjust(span).span())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::Spanned;
+use core::{
+    fmt::{self, Formatter},
+    marker::PhantomData,
+};
+use serde::{
+    de::{Error, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A single map entry, with the spans of both its key and its value.
+///
+/// This deserializes from a map containing exactly one entry. Use
+/// [`SpannedEntry::deserialize_all()`] to read every entry of a map in the
+/// order they were written, which is handy for diagnostics like "the key
+/// `timeout` here, with its value here".
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SpannedEntry<K, V> {
+    pub key: Spanned<K>,
+    pub value: Spanned<V>,
+}
+
+impl<K, V> SpannedEntry<K, V> {
+    pub const fn new(key: Spanned<K>, value: Spanned<V>) -> Self {
+        SpannedEntry { key, value }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, K, V> SpannedEntry<K, V>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    /// Deserialize every entry in a map, keeping the order they were
+    /// written in and allowing duplicate keys.
+    ///
+    /// This is meant to be used with `#[serde(deserialize_with = "...")]`.
+    ///
+    /// ```rust
+    /// use serde::de::{value::Error, Deserialize, IntoDeserializer};
+    /// use serde_derive::Deserialize;
+    /// use serde_spanned::{Spanned, SpannedEntry, SpannedValue};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     #[serde(deserialize_with = "SpannedEntry::deserialize_all")]
+    ///     env: Vec<SpannedEntry<String, String>>,
+    /// }
+    ///
+    /// // what a span-aware format would produce for
+    /// // `{"env": {"B": "1", "A": "2"}}`
+    /// let env = SpannedValue::Object(vec![
+    ///     (
+    ///         Spanned::new(String::from("B"), 9, 12),
+    ///         Spanned::new(SpannedValue::from("1"), 14, 17),
+    ///     ),
+    ///     (
+    ///         Spanned::new(String::from("A"), 19, 22),
+    ///         Spanned::new(SpannedValue::from("2"), 24, 27),
+    ///     ),
+    /// ]);
+    /// let value = SpannedValue::Object(vec![(
+    ///     Spanned::new(String::from("env"), 1, 6),
+    ///     Spanned::new(env, 8, 28),
+    /// )]);
+    /// let config =
+    ///     Config::deserialize(value.into_deserializer::<Error>()).unwrap();
+    ///
+    /// let keys: Vec<_> = config.env.iter().map(|e| e.key.value()).collect();
+    /// assert_eq!(keys, ["B", "A"]);
+    /// assert_eq!(config.env[1].value.span(), (24, 27));
+    /// ```
+    pub fn deserialize_all<D>(
+        de: D,
+    ) -> Result<alloc::vec::Vec<SpannedEntry<K, V>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        de.deserialize_map(EntriesVisitor(PhantomData))
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for SpannedEntry<K, V> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        let mut map = ser.serialize_map(Some(1))?;
+        map.serialize_entry(&self.key, &self.value)?;
+        map.end()
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for SpannedEntry<K, V>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_map(EntryVisitor(PhantomData))
+    }
+}
+
+struct EntryVisitor<K, V>(PhantomData<fn() -> (K, V)>);
+
+impl<'de, K, V> Visitor<'de> for EntryVisitor<K, V>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = SpannedEntry<K, V>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a map with a single entry")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Self::Value, A::Error> {
+        let (key, value) = map
+            .next_entry()?
+            .ok_or_else(|| Error::invalid_length(0, &self))?;
+
+        if map.next_key::<serde::de::IgnoredAny>()?.is_some() {
+            return Err(Error::custom("expected a map with a single entry"));
+        }
+
+        Ok(SpannedEntry { key, value })
+    }
+}
+
+#[cfg(feature = "alloc")]
+struct EntriesVisitor<K, V>(PhantomData<fn() -> (K, V)>);
+
+#[cfg(feature = "alloc")]
+impl<'de, K, V> Visitor<'de> for EntriesVisitor<K, V>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = alloc::vec::Vec<SpannedEntry<K, V>>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Self::Value, A::Error> {
+        let mut entries = alloc::vec::Vec::new();
+
+        while let Some((key, value)) = map.next_entry()? {
+            entries.push(SpannedEntry { key, value });
+        }
+
+        Ok(entries)
+    }
+}
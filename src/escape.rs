@@ -0,0 +1,215 @@
+use crate::{Span, Spanned};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Maps characters in a decoded string back to where they were written in
+/// the source, accounting for backslash escapes.
+///
+/// When a string contains escapes like `\n` or `\u00e9`, the `n`'th
+/// character of the decoded value isn't `n` characters into the literal.
+/// This lets a diagnostic point at a single character (e.g. an invalid
+/// regex token) inside a [`Spanned<String>`].
+///
+/// The escapes recognised are those shared by JSON, TOML, and YAML's
+/// double-quoted strings: a backslash followed by one character, `\xXX`,
+/// `\uXXXX` (including surrogate pairs), and `\UXXXXXXXX`, plus a
+/// backslash at the end of a line.
+///
+/// ```rust
+/// use serde_spanned::{EscapeMap, Span, Spanned};
+///
+/// let src = r#"pattern = "a\tb\u00e9c""#;
+/// let pattern = Spanned::new(String::from("a\tb\u{e9}c"), 10, 23);
+///
+/// let map = EscapeMap::for_string(src, &pattern).unwrap();
+///
+/// // the "é" is the 4th character in the decoded value
+/// let e = map.span(3..4).unwrap();
+/// assert_eq!(&src[e.as_range()], r"\u00e9");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeMap {
+    /// The source offset of each decoded character, followed by the offset
+    /// just after the last one.
+    offsets: Vec<usize>,
+}
+
+impl EscapeMap {
+    /// Scan the text inside a string literal (without its quotes), where
+    /// `start` is the literal's offset in the source.
+    ///
+    /// A backslash at the end of a line (TOML's line ending backslash, or an
+    /// escaped line break in YAML) decodes to nothing, along with the
+    /// whitespace and line breaks which follow it.
+    pub fn new(literal: &str, start: usize) -> Self {
+        let bytes = literal.as_bytes();
+        let mut offsets = Vec::new();
+        let mut ix = 0;
+
+        while ix < bytes.len() {
+            if bytes[ix] == b'\\' {
+                if let Some(len) = line_ending_backslash_len(&bytes[ix..]) {
+                    ix += len;
+                    continue;
+                }
+            }
+
+            offsets.push(start + ix);
+
+            ix += if bytes[ix] == b'\\' {
+                escape_len(&bytes[ix..])
+            } else {
+                utf8_len(bytes[ix])
+            };
+        }
+
+        offsets.push(start + literal.len());
+
+        EscapeMap { offsets }
+    }
+
+    /// Scan the text inside a string literal which doesn't support escapes,
+    /// like TOML's single-quoted literal strings.
+    pub fn raw(literal: &str, start: usize) -> Self {
+        let offsets = literal
+            .char_indices()
+            .map(|(ix, _)| start + ix)
+            .chain(core::iter::once(start + literal.len()))
+            .collect();
+
+        EscapeMap { offsets }
+    }
+
+    /// Build the map for a deserialized string, using the text its span
+    /// covers in `src`.
+    ///
+    /// Surrounding quotes are skipped, including TOML's `"""` and `'''`
+    /// multi-line delimiters and the line break which may follow the
+    /// opening one. Single-quoted strings are taken literally, without any
+    /// escapes. Text without quotes is treated like the inside of a
+    /// double-quoted string. Returns `None` if the span isn't inside `src`.
+    pub fn for_string<T>(src: &str, spanned: &Spanned<T>) -> Option<Self> {
+        let (start, end) = spanned.span();
+        let text = src.get(start..end)?;
+
+        let delimited = |delimiter: &str| {
+            text.len() >= 2 * delimiter.len()
+                && text.starts_with(delimiter)
+                && text.ends_with(delimiter)
+        };
+
+        let (quote, multi_line) = if delimited("\"\"\"") {
+            ("\"\"\"", true)
+        } else if delimited("'''") {
+            ("'''", true)
+        } else if delimited("\"") {
+            ("\"", false)
+        } else if delimited("'") {
+            ("'", false)
+        } else {
+            return Some(EscapeMap::new(text, start));
+        };
+
+        let mut inner = &text[quote.len()..text.len() - quote.len()];
+        let mut inner_start = start + quote.len();
+
+        if multi_line {
+            let newline = if inner.starts_with("\r\n") {
+                2
+            } else if inner.starts_with('\n') {
+                1
+            } else {
+                0
+            };
+            inner = &inner[newline..];
+            inner_start += newline;
+        }
+
+        if quote.starts_with('\'') {
+            Some(EscapeMap::raw(inner, inner_start))
+        } else {
+            Some(EscapeMap::new(inner, inner_start))
+        }
+    }
+
+    /// The number of characters in the decoded string.
+    pub fn len(&self) -> usize { self.offsets.len() - 1 }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// The source offset of the character at `index` in the decoded string.
+    ///
+    /// The index just past the last character maps to the end of the
+    /// literal.
+    pub fn offset(&self, index: usize) -> Option<usize> {
+        self.offsets.get(index).copied()
+    }
+
+    /// The source span covering a range of characters in the decoded
+    /// string.
+    pub fn span(&self, chars: Range<usize>) -> Option<Span> {
+        if chars.start > chars.end {
+            return None;
+        }
+
+        Some(Span::new(
+            self.offset(chars.start)?,
+            self.offset(chars.end)?,
+        ))
+    }
+}
+
+/// The length of the escape sequence at the start of `escape`, which starts
+/// with a backslash.
+fn escape_len(escape: &[u8]) -> usize {
+    let hex_digits = match escape.get(1) {
+        Some(b'x') => 2,
+        Some(b'u') => 4,
+        Some(b'U') => 8,
+        Some(&other) => return 1 + utf8_len(other),
+        None => return 1,
+    };
+    let len = (2 + hex_digits).min(escape.len());
+
+    // A UTF-16 surrogate pair is written as two \u escapes, but decodes to
+    // a single character
+    let is_high_surrogate = hex_digits == 4
+        && matches!(escape.get(2), Some(b'd') | Some(b'D'))
+        && matches!(
+            escape.get(3),
+            Some(b'8'..=b'9') | Some(b'a'..=b'b') | Some(b'A'..=b'B')
+        );
+
+    if is_high_surrogate && escape[len..].starts_with(b"\\u") {
+        (len + 6).min(escape.len())
+    } else {
+        len
+    }
+}
+
+/// The length of a backslash at the end of a line (`\`, optional spaces
+/// or tabs, then a line break) along with the whitespace and line breaks
+/// after it, or `None` if `escape` doesn't start with one.
+fn line_ending_backslash_len(escape: &[u8]) -> Option<usize> {
+    let is_space = |b: &u8| *b == b' ' || *b == b'\t';
+    let is_whitespace = |b: &u8| is_space(b) || *b == b'\n' || *b == b'\r';
+
+    let spaces = escape[1..].iter().take_while(|b| is_space(b)).count();
+    let after_spaces = &escape[1 + spaces..];
+
+    if !(after_spaces.starts_with(b"\n") || after_spaces.starts_with(b"\r\n")) {
+        return None;
+    }
+
+    let trimmed = after_spaces.iter().take_while(|b| is_whitespace(b)).count();
+    Some(1 + spaces + trimmed)
+}
+
+fn utf8_len(first_byte: u8) -> usize {
+    match first_byte {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
@@ -0,0 +1,95 @@
+use crate::Spanned;
+use core::{
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+    str::FromStr,
+};
+use serde::{
+    de::{Error, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A value written as a string in the document and parsed with [`FromStr`],
+/// keeping the span of the string.
+///
+/// If parsing fails, the error message includes the span of the string so
+/// it can be pointed out to the user, instead of just the parse error.
+///
+/// ```rust
+/// use serde::de::{
+///     value::{Error, MapAccessDeserializer},
+///     Deserialize, IntoDeserializer,
+/// };
+/// use serde_spanned::{de::SpannedDeserializer, SpannedFromStr};
+/// use std::net::SocketAddr;
+///
+/// // what a span-aware format would produce for `listen = "127.0.0.1:80"`
+/// let text = IntoDeserializer::<Error>::into_deserializer("127.0.0.1:80");
+/// let de = MapAccessDeserializer::new(SpannedDeserializer::new(9, 23, text));
+///
+/// let listen = SpannedFromStr::<SocketAddr>::deserialize(de).unwrap();
+///
+/// assert_eq!(listen.0.value().port(), 80);
+/// assert_eq!(listen.0.span(), (9, 23));
+/// ```
+///
+/// Serializing writes the value's [`Display`] form as a string.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SpannedFromStr<T>(pub Spanned<T>);
+
+impl<T> SpannedFromStr<T> {
+    pub fn into_inner(self) -> Spanned<T> { self.0 }
+}
+
+impl<T> From<Spanned<T>> for SpannedFromStr<T> {
+    fn from(spanned: Spanned<T>) -> Self { SpannedFromStr(spanned) }
+}
+
+impl<T: Display> Serialize for SpannedFromStr<T> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_str(self.0.value())
+    }
+}
+
+impl<'de, T> Deserialize<'de> for SpannedFromStr<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        // The span may not be known until after the value is deserialized,
+        // so hold on to the parse result and only report it afterwards
+        let parsed = Spanned::<Parsed<T>>::deserialize(de)?;
+        let (start, end) = parsed.span();
+
+        match parsed.into_value().0 {
+            Ok(value) => Ok(SpannedFromStr(Spanned::new(value, start, end))),
+            Err(e) => Err(D::Error::custom(format_args!(
+                "{} (at {}..{})",
+                e, start, end
+            ))),
+        }
+    }
+}
+
+struct Parsed<T: FromStr>(Result<T, T::Err>);
+
+impl<'de, T: FromStr> Deserialize<'de> for Parsed<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_str(ParsedVisitor(PhantomData))
+    }
+}
+
+struct ParsedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: FromStr> Visitor<'de> for ParsedVisitor<T> {
+    type Value = Parsed<T>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: Error>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(Parsed(s.parse()))
+    }
+}
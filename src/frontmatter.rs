@@ -0,0 +1,125 @@
+use crate::Spanned;
+
+/// The fence used to delimit a document's frontmatter.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Fence {
+    /// `---`, conventionally used for YAML frontmatter.
+    Dashes,
+    /// `+++`, conventionally used for TOML frontmatter.
+    Pluses,
+}
+
+impl Fence {
+    const ALL: [Fence; 2] = [Fence::Dashes, Fence::Pluses];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Fence::Dashes => "---",
+            Fence::Pluses => "+++",
+        }
+    }
+
+    fn matches(self, line: &str) -> bool { line.trim_end() == self.as_str() }
+}
+
+/// The raw text between a document's frontmatter fences.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Frontmatter<'a> {
+    pub fence: Fence,
+    pub text: &'a str,
+}
+
+/// Split a document into its frontmatter and the remaining body.
+///
+/// Both spans are byte offsets into `src`. The frontmatter's span only covers
+/// the text between the fences, so spans reported while deserializing
+/// [`Frontmatter::text`] can be translated into whole-file coordinates by
+/// adding its `start()`.
+///
+/// A leading byte order mark is skipped, see [`strip_bom()`].
+///
+/// Returns `None` if the document doesn't start with a fence or the fence is
+/// never closed.
+pub fn split_frontmatter(
+    src: &str,
+) -> Option<(Spanned<Frontmatter<'_>>, Spanned<&str>)> {
+    let mut lines = Lines {
+        src,
+        cursor: strip_bom(src).start(),
+    };
+
+    let (_, opening) = lines.next()?;
+    let fence = Fence::ALL.iter().copied().find(|f| f.matches(opening))?;
+    let text_start = lines.cursor;
+
+    for (line_start, line) in &mut lines {
+        if fence.matches(line) {
+            let text = &src[text_start..line_start];
+            let frontmatter = Spanned::new(
+                Frontmatter { fence, text },
+                text_start,
+                line_start,
+            );
+            let body_start = lines.cursor;
+            let body = Spanned::new(&src[body_start..], body_start, src.len());
+
+            return Some((frontmatter, body));
+        }
+    }
+
+    None
+}
+
+/// Skip the UTF-8 byte order mark some editors write at the start of a
+/// file.
+///
+/// The returned span is relative to `src`, so spans reported while
+/// deserializing the text (which are relative to the text after the BOM)
+/// can be translated back into raw byte offsets by shifting them by its
+/// `start()`. Leave them as they are if line and column numbers should
+/// ignore the BOM.
+///
+/// ```rust
+/// use serde_spanned::{strip_bom, Span};
+///
+/// let text = strip_bom("\u{feff}port = 8080");
+/// assert_eq!(*text.value(), "port = 8080");
+///
+/// let port = Span::new(7, 11);
+/// assert_eq!(port.shifted(text.start()), Span::new(10, 14));
+/// ```
+pub fn strip_bom(src: &str) -> Spanned<&str> {
+    let start = if src.starts_with(BOM) {
+        BOM.len_utf8()
+    } else {
+        0
+    };
+    Spanned::new(&src[start..], start, src.len())
+}
+
+const BOM: char = '\u{feff}';
+
+/// Iterate over `(offset, line)` pairs, where each line includes its
+/// terminating newline.
+struct Lines<'a> {
+    src: &'a str,
+    cursor: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.cursor;
+        let rest = &self.src[start..];
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        let len = rest.find('\n').map(|ix| ix + 1).unwrap_or(rest.len());
+        self.cursor += len;
+
+        Some((start, &rest[..len]))
+    }
+}
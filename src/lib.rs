@@ -1,49 +1,307 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "test-util")]
+pub mod conformance;
+mod content_hash;
+pub mod de;
+mod document;
+mod edit;
+mod entry;
+#[cfg(feature = "alloc")]
+mod escape;
+mod from_str;
+mod frontmatter;
+pub mod meta;
+#[cfg(feature = "alloc")]
+mod other;
+mod position;
+mod respan;
+mod secret;
+pub mod snapshot;
+mod span;
+mod total_ord;
+mod unspanned;
+#[cfg(feature = "alloc")]
+mod validate;
+#[cfg(feature = "alloc")]
+pub mod value;
+pub mod walk;
+mod with_span;
+
+#[cfg(feature = "std")]
+pub use crate::with_span::{BinarySpans, ExplicitSpans};
+#[doc(hidden)]
+pub use crate::{content_hash::__StructHasher, macros::__check_span};
+pub use crate::{
+    content_hash::{content_hash, ContentHash, ContentHashError},
+    document::Document,
+    edit::{Adjusted, TextEdit},
+    entry::SpannedEntry,
+    from_str::SpannedFromStr,
+    frontmatter::{split_frontmatter, strip_bom, Fence, Frontmatter},
+    meta::FieldsMeta,
+    position::{Columns, LineColumn, Position},
+    respan::Respan,
+    secret::Secret,
+    span::{InvalidSpan, Span, SpanLines},
+    total_ord::TotalOrd,
+    unspanned::Unspanned,
+    walk::WalkSpans,
+    with_span::WithSpan,
+};
+#[cfg(feature = "alloc")]
+pub use crate::{
+    escape::EscapeMap,
+    other::SpannedOther,
+    position::{LineIndex, NormalizedNewlines},
+    respan::SpanMap,
+    validate::{Located, ValidationError},
+    value::{Number, SpannedValue},
+};
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use alloc::format as __format;
+#[cfg(feature = "derive")]
+pub use serde_spanned_derive::{
+    with_spans, ContentHash, FieldsMeta, Respan, SpannedFields, Unspanned,
+    WalkSpans,
+};
+
 use core::{
     borrow::{Borrow, BorrowMut},
-    fmt::{self, Formatter},
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
     marker::PhantomData,
 };
 use serde::{
-    de::{Error, MapAccess},
+    de::{DeserializeSeed, Error, MapAccess, SeqAccess},
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
+// With the `toml-compat` feature the protocol uses the same strings as the
+// `toml` crate's `Spanned`, so this crate's `Spanned` works with `toml`'s
+// deserializer, and `toml::Spanned` works with deserializers built on
+// `de::SpannedDeserializer` (but not `de::StreamingSpanned`, whose keys are
+// in a different order).
+
+#[cfg(not(feature = "toml-compat"))]
 pub const NAME: &str = "$__serde_private_Spanned";
+#[cfg(not(feature = "toml-compat"))]
 pub const START: &str = "$__serde_private_start";
+#[cfg(not(feature = "toml-compat"))]
 pub const END: &str = "$__serde_private_end";
+#[cfg(not(feature = "toml-compat"))]
 pub const VALUE: &str = "$__serde_private_value";
 
+#[cfg(feature = "toml-compat")]
+pub const NAME: &str = "$__toml_private_Spanned";
+#[cfg(feature = "toml-compat")]
+pub const START: &str = "$__toml_private_start";
+#[cfg(feature = "toml-compat")]
+pub const END: &str = "$__toml_private_end";
+#[cfg(feature = "toml-compat")]
+pub const VALUE: &str = "$__toml_private_value";
+
 pub const FIELDS: &[&str] = &[START, END, VALUE];
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// The prefix shared by [`START`], [`END`], and [`VALUE`].
+#[cfg(not(feature = "toml-compat"))]
+const PRIVATE_PREFIX: &str = "$__serde_private_";
+#[cfg(feature = "toml-compat")]
+const PRIVATE_PREFIX: &str = "$__toml_private_";
+
+/// A value along with the location it was deserialized from.
+///
+/// Because [`Span`] has a niche, `Option<Spanned<T>>` is the same size as
+/// `Spanned<T>`.
+///
+/// # Optional Fields
+///
+/// A missing `Option<Spanned<T>>` field is always `None`, regardless of the
+/// format. `serde_derive` handles missing `Option` fields before `Spanned`
+/// is involved, so this works even with formats which don't support spans.
+///
+/// ```rust
+/// use serde::de::{value::{Error, MapDeserializer}, Deserialize};
+/// use serde_derive::Deserialize;
+/// use serde_spanned::Spanned;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     port: Option<Spanned<u16>>,
+/// }
+///
+/// let empty = MapDeserializer::<_, Error>::new(std::iter::empty::<(&str, u16)>());
+/// let config = Config::deserialize(empty).unwrap();
+///
+/// assert!(config.port.is_none());
+/// ```
+///
+/// A field which is present but `null` is up to the format. Most formats
+/// call `Visitor::visit_none()` and the field is `None`, but a format which
+/// attaches a span to the `null` itself may produce an error instead.
+///
+/// # Nesting
+///
+/// A `Spanned<Spanned<T>>` is allowed, and both layers get the same span
+/// when deserialized from a span-aware format. [`Unspanned`] strips every
+/// layer at once.
+///
+/// When serializing, [`ExplicitSpans`] writes the span of every layer, so
+/// nested values round-trip exactly. [`Spanned::with_span()`] only writes
+/// the outermost span and the inner layers are transparent. The explicit
+/// `{ value, start, end }` form is accepted at any depth when deserializing.
+///
+/// # Self-Describing Values
+///
+/// `T` may be a type which deserializes itself with
+/// `Deserializer::deserialize_any()`, such as `serde_json::Value`. The span
+/// covers whatever value was in the document, whether it is a scalar, a
+/// sequence, or a map.
+///
+/// The same goes for enums which are deserialized from numbers, such as
+/// those using `serde_repr`. A `Spanned<Level>` covers the numeric literal
+/// the variant was read from.
+///
+/// A `Spanned<char>` covers the string it was read from. Byte strings
+/// (e.g. `Spanned<serde_bytes::ByteBuf>`) cover the literal they were read
+/// from. Formats without a byte string type, like TOML, hand over an array
+/// of integers or a string instead, which `serde_bytes` accepts.
+///
+/// # Multi-Line Strings
+///
+/// The span of a multi-line string covers the whole literal as the format
+/// reports it. For TOML that includes the `"""` or `'''` delimiters and
+/// the line break after the opening delimiter, which aren't part of the
+/// value.
+/// YAML block scalars similarly start at the `|` or `>` indicator, and their
+/// indentation is stripped from the value.
+///
+/// Use [`Span::lines()`] to get the span of each source line inside the
+/// literal, so a diagnostic can point at the right line.
+///
+/// # Binary Formats
+///
+/// Deserializers which aren't human-readable (see
+/// `Deserializer::is_human_readable()`) usually have no source text to
+/// point into, so `Spanned<T>` deserializes `T` directly and uses an empty
+/// `0..0` span. Wrap the deserializer in [`de::HumanReadable`] (or, with
+/// the `std` feature, the value in [`BinarySpans`]) for binary formats which
+/// do support spans. Values wrapped in [`ExplicitSpans`] always keep their
+/// spans.
+///
+/// # Buffered Values
+///
+/// Some `serde_derive` representations buffer a value in memory before
+/// deserializing it. The buffer doesn't know about spans, so any `Spanned`
+/// *inside* a buffered value fails to deserialize, with an error like
+/// "invalid type: integer `5`, expected A spanned u32".
+///
+/// Internally tagged enums (`#[serde(tag = "type")]`) always buffer their
+/// variant's content. Wrap the whole enum in `Spanned` to get the span of
+/// the variant, and keep the fields inside the variants unspanned.
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// #[serde(tag = "type")]
+/// enum Shape {
+///     Circle { radius: f64 },
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Drawing {
+///     // works, and covers the whole `{ "type": "Circle", ... }` map
+///     shapes: Vec<Spanned<Shape>>,
+/// }
+/// ```
+///
+/// If the spans of individual fields are needed, use an externally tagged
+/// enum (serde's default), which isn't buffered.
+///
+/// Adjacently tagged enums (`#[serde(tag = "t", content = "c")]`) only
+/// buffer the content when it comes before the tag in the document. Fields
+/// inside the content keep their spans as long as the tag is written
+/// first, and wrapping the whole enum in `Spanned` works either way.
+///
+/// # Comparisons
+///
+/// Equality, ordering, and hashing only look at the value, so a `Spanned<T>`
+/// can be used as a map key and looked up with a plain `T` (or a `&str` for
+/// `Spanned<String>`).
+///
+/// This is a breaking change from earlier versions, where two values were
+/// only equal if their spans were too. Compare [`Spanned::span()`]
+/// explicitly when the location matters.
+///
+/// ```rust
+/// use serde_spanned::Spanned;
+/// use std::collections::HashMap;
+///
+/// let first = Spanned::new(String::from("port"), 0, 6);
+/// let second = Spanned::new(String::from("port"), 20, 26);
+/// assert_eq!(first, second);
+/// assert_ne!(first.span(), second.span());
+///
+/// let mut map = HashMap::new();
+/// map.insert(first, 8080);
+/// assert_eq!(map[&String::from("port")], 8080);
+/// ```
+#[derive(Default, Copy, Clone)]
 pub struct Spanned<T> {
     value: T,
-    start: usize,
-    end: usize,
+    span: Span,
 }
 
 impl<T> Spanned<T> {
+    /// Create a spanned value without checking the span, see [`Span::new()`].
     pub const fn new(value: T, start: usize, end: usize) -> Self {
-        Spanned { value, start, end }
+        Spanned::from_span(value, Span::new(start, end))
+    }
+
+    /// Create a spanned value, checking that the span doesn't end before it
+    /// starts.
+    pub fn try_new(
+        value: T,
+        start: usize,
+        end: usize,
+    ) -> Result<Self, InvalidSpan> {
+        Span::try_new(start, end).map(|span| Spanned::from_span(value, span))
+    }
+
+    pub const fn from_span(value: T, span: Span) -> Self {
+        Spanned { value, span }
     }
 
-    pub const fn start(&self) -> usize { self.start }
+    pub const fn start(&self) -> usize { self.span.start() }
+
+    pub const fn end(&self) -> usize { self.span.end() }
 
-    pub const fn end(&self) -> usize { self.end }
+    pub const fn span(&self) -> (usize, usize) { self.span.as_tuple() }
 
-    pub const fn span(&self) -> (usize, usize) { (self.start(), self.end()) }
+    /// The span as a [`Span`].
+    pub const fn to_span(&self) -> Span { self.span }
 
-    pub const fn len(&self) -> usize { self.end() - self.start() }
+    pub const fn len(&self) -> usize { self.span.len() }
 
-    pub const fn is_empty(&self) -> bool { self.len() == 0 }
+    pub const fn is_empty(&self) -> bool { self.span.is_empty() }
 
     pub const fn value(&self) -> &T { &self.value }
 
     pub fn value_mut(&mut self) -> &mut T { &mut self.value }
 
     pub fn into_value(self) -> T { self.value }
+
+    /// Serialize this value along with its span, instead of transparently.
+    pub const fn with_span(&self) -> WithSpan<&Self> { WithSpan(self) }
 }
 
 impl<T, Q> AsRef<Q> for Spanned<T>
@@ -60,6 +318,20 @@ where
     fn as_mut(&mut self) -> &mut Q { self.value_mut().as_mut() }
 }
 
+impl<T: fmt::Debug> fmt::Debug for Spanned<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spanned")
+            .field("value", &self.value)
+            .field("start", &self.start())
+            .field("end", &self.end())
+            .finish()
+    }
+}
+
+impl<T: Display> Display for Spanned<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { self.value().fmt(f) }
+}
+
 impl<T> Borrow<T> for Spanned<T> {
     fn borrow(&self) -> &T { self.value() }
 }
@@ -68,52 +340,194 @@ impl<T> BorrowMut<T> for Spanned<T> {
     fn borrow_mut(&mut self) -> &mut T { self.value_mut() }
 }
 
+#[cfg(feature = "alloc")]
+impl Borrow<str> for Spanned<alloc::string::String> {
+    fn borrow(&self) -> &str { self.value() }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool { self.value() == other.value() }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+impl<T: PartialOrd> PartialOrd for Spanned<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value().partial_cmp(other.value())
+    }
+}
+
+impl<T: Ord> Ord for Spanned<T> {
+    fn cmp(&self, other: &Self) -> Ordering { self.value().cmp(other.value()) }
+}
+
+impl<T: Hash> Hash for Spanned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.value().hash(state) }
+}
+
 impl<T: Serialize> Serialize for Spanned<T> {
     fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
-        self.value.serialize(ser)
+        if with_span::is_explicit() {
+            self.with_span().serialize(ser)
+        } else {
+            self.value.serialize(ser)
+        }
     }
 }
 
 impl<'de, T: Deserialize<'de>> Deserialize<'de> for Spanned<T> {
     fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
-        de.deserialize_struct(NAME, FIELDS, Visitor(PhantomData))
+        SpannedSeed(PhantomData::<T>).deserialize(de)
     }
 }
 
-struct Visitor<T>(PhantomData<T>);
+/// Deserialize a [`Spanned`] value, using a [`DeserializeSeed`] for the
+/// value inside it.
+pub(crate) struct SpannedSeed<S>(pub(crate) S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for SpannedSeed<S> {
+    type Value = Spanned<S::Value>;
 
-impl<'de, T> serde::de::Visitor<'de> for Visitor<T>
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<Self::Value, D::Error> {
+        if with_span::is_explicit() {
+            de.deserialize_struct(
+                with_span::NAME,
+                with_span::FIELDS,
+                Visitor(self.0),
+            )
+        } else if !de.is_human_readable() && !with_span::spans_in_binary() {
+            self.0
+                .deserialize(de)
+                .map(|value| Spanned::new(value, 0, 0))
+        } else {
+            de.deserialize_struct(NAME, FIELDS, Visitor(self.0))
+        }
+    }
+}
+
+struct Visitor<S>(S);
+
+/// What a [`Visitor`] expects, once its seed has been used up.
+struct Expecting<T>(PhantomData<T>);
+
+impl<T> serde::de::Expected for Expecting<T> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "A spanned {}", core::any::type_name::<T>())
+    }
+}
+
+impl<'de, S> serde::de::Visitor<'de> for Visitor<S>
 where
-    T: Deserialize<'de>,
+    S: DeserializeSeed<'de>,
 {
-    type Value = Spanned<T>;
+    type Value = Spanned<S::Value>;
 
     fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
-        write!(formatter, "A spanned {}", core::any::type_name::<T>())
+        serde::de::Expected::fmt(&Expecting(PhantomData::<S::Value>), formatter)
     }
 
     fn visit_map<A>(self, mut visitor: A) -> Result<Self::Value, A::Error>
     where
         A: MapAccess<'de>,
     {
-        if visitor.next_key()? != Some(crate::START) {
-            return Err(Error::custom("spanned start key not found"));
+        let mut start = None;
+        let mut end = None;
+        let mut seed = Some(self.0);
+        let mut value = None;
+
+        while let Some(field) = visitor.next_key()? {
+            match field {
+                Field::Start if start.is_none() => {
+                    start = Some(visitor.next_value()?);
+                },
+                Field::End if end.is_none() => {
+                    end = Some(visitor.next_value()?);
+                },
+                Field::Value if seed.is_some() => {
+                    let seed = seed.take().expect("checked above");
+                    value = Some(visitor.next_value_seed(seed)?);
+                },
+                _ => return Err(Error::custom("duplicate spanned key")),
+            }
         }
 
-        let start: usize = visitor.next_value()?;
+        let start = start
+            .ok_or_else(|| Error::custom("spanned start key not found"))?;
+        let end =
+            end.ok_or_else(|| Error::custom("spanned end key not found"))?;
+        let value = value
+            .ok_or_else(|| Error::custom("spanned value key not found"))?;
 
-        if visitor.next_key()? != Some(crate::END) {
-            return Err(Error::custom("spanned end key not found"));
-        }
+        Spanned::try_new(value, start, end).map_err(Error::custom)
+    }
 
-        let end: usize = visitor.next_value()?;
+    /// Accept the explicit representation, `(value, start, end)`, from
+    /// formats which deserialize structs as sequences.
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let expected = Expecting(PhantomData::<S::Value>);
+        let value = seq
+            .next_element_seed(self.0)?
+            .ok_or_else(|| Error::invalid_length(0, &expected))?;
+        let start = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(1, &expected))?;
+        let end = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(2, &expected))?;
 
-        if visitor.next_key()? != Some(crate::VALUE) {
-            return Err(Error::custom("spanned value key not found"));
-        }
+        Spanned::try_new(value, start, end).map_err(Error::custom)
+    }
+}
+
+/// The keys a [`Visitor`] will accept, using either the private protocol
+/// names or the names used by [`WithSpan`].
+enum Field {
+    Start,
+    End,
+    Value,
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_identifier(FieldVisitor)
+    }
+}
+
+struct FieldVisitor;
+
+impl<'de> serde::de::Visitor<'de> for FieldVisitor {
+    type Value = Field;
 
-        let value: T = visitor.next_value()?;
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("a spanned key")
+    }
 
-        Ok(Spanned { start, end, value })
+    fn visit_str<E: Error>(self, key: &str) -> Result<Self::Value, E> {
+        // Keys are checked for every spanned value, so pick the only possible
+        // candidate using a single byte and do one full comparison against it
+        let bytes = key.as_bytes();
+        let candidate = match bytes.first() {
+            Some(b'$') => match bytes.get(PRIVATE_PREFIX.len()) {
+                Some(b's') => Some((START, Field::Start)),
+                Some(b'e') => Some((END, Field::End)),
+                Some(b'v') => Some((VALUE, Field::Value)),
+                _ => None,
+            },
+            Some(b's') => Some((with_span::START, Field::Start)),
+            Some(b'e') => Some((with_span::END, Field::End)),
+            Some(b'v') => Some((with_span::VALUE, Field::Value)),
+            _ => None,
+        };
+
+        match candidate {
+            Some((expected, field)) if key == expected => Ok(field),
+            _ => Err(Error::unknown_field(key, FIELDS)),
+        }
     }
 }
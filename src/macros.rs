@@ -0,0 +1,200 @@
+/// Get the span of a field nested inside several layers of [`Spanned`]
+/// structs.
+///
+/// `span_of!(cfg.server.tls.cert)` expands to
+/// `cfg.server.value().tls.value().cert.span()`. This is what you would write
+/// by hand when working with companion structs generated by
+/// `#[derive(SpannedFields)]` and `#[spanned_fields(nested)]`.
+///
+/// The first item may be a variable or a parenthesized expression.
+///
+/// ```rust
+/// use serde_spanned::{span_of, Spanned};
+///
+/// struct Tls {
+///     cert: Spanned<&'static str>,
+/// }
+///
+/// struct Server {
+///     tls: Spanned<Tls>,
+/// }
+///
+/// let server = Server {
+///     tls: Spanned::new(Tls { cert: Spanned::new("cert.pem", 12, 22) }, 5, 30),
+/// };
+///
+/// assert_eq!(span_of!(server.tls), (5, 30));
+/// assert_eq!(span_of!(server.tls.cert), (12, 22));
+/// ```
+///
+/// [`Spanned`]: crate::Spanned
+#[macro_export]
+macro_rules! span_of {
+    (@walk ($value:expr) $field:tt) => {
+        $crate::Spanned::span(&$value.$field)
+    };
+    (@walk ($value:expr) $field:tt $($rest:tt)+) => {
+        $crate::span_of!(@walk ($crate::Spanned::value(&$value.$field)) $($rest)+)
+    };
+    (($root:expr) $(. $field:tt)+) => {
+        $crate::span_of!(@walk ($root) $($field)+)
+    };
+    ($root:ident $(. $field:tt)+) => {
+        $crate::span_of!(@walk ($root) $($field)+)
+    };
+}
+
+/// Assert that a [`Spanned`] value's span covers exactly `expected` in the
+/// original source text.
+///
+/// ```rust
+/// use serde_spanned::{assert_span_eq, Spanned};
+///
+/// let src = "port = 8080";
+/// let port = Spanned::new(8080, 7, 11);
+///
+/// assert_span_eq!(src, port, "8080");
+/// ```
+///
+/// On failure the message shows the text the span actually covers and where
+/// the expected text appears in the source.
+///
+/// [`Spanned`]: crate::Spanned
+#[macro_export]
+macro_rules! assert_span_eq {
+    ($src:expr, $spanned:expr, $expected:expr $(,)?) => {
+        if let ::core::result::Result::Err(mismatch) =
+            $crate::__check_span($src, ($spanned).span(), $expected)
+        {
+            ::core::panic!("{}", mismatch);
+        }
+    };
+}
+
+/// Assert that a [`Spanned`] value has the expected value and that its span
+/// covers exactly the expected text in the original source.
+///
+/// ```rust
+/// use serde_spanned::{assert_spanned, Spanned};
+///
+/// let src = r#"{ "name": "serde" }"#;
+/// let name = Spanned::new("serde", 10, 17);
+///
+/// assert_spanned!(src, name, "serde", r#""serde""#);
+/// ```
+///
+/// [`Spanned`]: crate::Spanned
+#[macro_export]
+macro_rules! assert_spanned {
+    ($src:expr, $spanned:expr, $value:expr, $expected:expr $(,)?) => {{
+        let spanned = &$spanned;
+        ::core::assert_eq!(
+            *spanned.value(),
+            $value,
+            "the spanned value is different",
+        );
+        $crate::assert_span_eq!($src, spanned, $expected);
+    }};
+}
+
+/// Return a [`ValidationError`] from the current function unless a
+/// condition holds.
+///
+/// The condition is followed by the span which broke the rule, an optional
+/// list of related spans, and a `format!()`-style message. The error is
+/// converted with `From`, so it works in functions returning any error type
+/// which can be created from a `ValidationError`.
+///
+/// ```rust
+/// use serde_spanned::{ensure, Spanned, ValidationError};
+///
+/// fn check_port(port: &Spanned<u16>) -> Result<(), ValidationError> {
+///     ensure!(*port.value() != 0, port.span(), "port {} is reserved", port);
+///     Ok(())
+/// }
+///
+/// assert!(check_port(&Spanned::new(0, 7, 8)).is_err());
+/// ```
+///
+/// [`ValidationError`]: crate::ValidationError
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $primary:expr, [$($related:expr),* $(,)?], $($msg:tt)+) => {
+        if !$cond {
+            return ::core::result::Result::Err(::core::convert::From::from(
+                $crate::ValidationError::new(
+                    $crate::__format!($($msg)+),
+                    $primary,
+                )
+                $(.with_related($related))*
+            ));
+        }
+    };
+    ($cond:expr, $primary:expr, $($msg:tt)+) => {
+        $crate::ensure!($cond, $primary, [], $($msg)+)
+    };
+}
+
+#[doc(hidden)]
+pub fn __check_span<'a>(
+    src: &'a str,
+    span: (usize, usize),
+    expected: &'a str,
+) -> Result<(), SpanMismatch<'a>> {
+    let actual = src.get(span.0..span.1);
+
+    if actual == Some(expected) {
+        Ok(())
+    } else {
+        Err(SpanMismatch {
+            span,
+            actual,
+            expected,
+            expected_at: src.find(expected),
+        })
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct SpanMismatch<'a> {
+    span: (usize, usize),
+    actual: Option<&'a str>,
+    expected: &'a str,
+    expected_at: Option<usize>,
+}
+
+impl core::fmt::Display for SpanMismatch<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (start, end) = self.span;
+
+        writeln!(f, "the span doesn't cover the expected text")?;
+
+        match self.expected_at {
+            Some(ix) => writeln!(
+                f,
+                " expected: {:?} (found at {}..{})",
+                self.expected,
+                ix,
+                ix + self.expected.len()
+            )?,
+            None => writeln!(
+                f,
+                " expected: {:?} (not found in the source)",
+                self.expected
+            )?,
+        }
+
+        match self.actual {
+            Some(actual) => {
+                write!(f, "   actual: {:?} at {}..{}", actual, start, end)
+            },
+            None => write!(
+                f,
+                "   actual: {}..{} is not a valid range in the source",
+                start, end
+            ),
+        }
+    }
+}
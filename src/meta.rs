@@ -0,0 +1,45 @@
+//! Information about a struct's fields which is available at runtime.
+//!
+//! This is useful for things like suggesting the correct spelling of an
+//! unknown key or offering completions in an editor.
+
+/// A struct which knows which fields may appear in its serialized form.
+///
+/// This can be derived with `#[derive(FieldsMeta)]` when the `derive`
+/// feature is enabled. The derive respects `#[serde(rename = "...")]`,
+/// `#[serde(rename_all = "...")]`, and `#[serde(alias = "...")]`, and leaves
+/// out skipped and flattened fields.
+pub trait FieldsMeta {
+    /// Every field, in declaration order.
+    const FIELDS: &'static [FieldMeta];
+
+    /// Find the field a key refers to, checking both names and aliases.
+    fn field_meta(key: &str) -> Option<&'static FieldMeta> {
+        Self::FIELDS.iter().find(|field| field.matches(key))
+    }
+}
+
+/// Metadata for a single field.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FieldMeta {
+    /// The field's name in the serialized document.
+    pub name: &'static str,
+    /// Other names accepted when deserializing.
+    pub aliases: &'static [&'static str],
+    /// The field's type, as written in the source code.
+    pub ty: &'static str,
+    /// The field's doc comment, with one line per line of documentation.
+    pub docs: &'static str,
+}
+
+impl FieldMeta {
+    /// Does `key` refer to this field?
+    pub fn matches(&self, key: &str) -> bool {
+        self.names().any(|name| name == key)
+    }
+
+    /// The field's name, followed by any aliases.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        core::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+}
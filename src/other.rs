@@ -0,0 +1,93 @@
+use crate::Spanned;
+use alloc::string::String;
+use serde::{
+    de::{DeserializeOwned, IntoDeserializer},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A field-less enum which keeps any unrecognised variant, along with where
+/// it was written.
+///
+/// `#[serde(other)]` can only be used on a unit variant, so the text of the
+/// unknown tag is lost. Use `SpannedOther<Mode>` instead of a catch-all
+/// variant when you want to warn about it.
+///
+/// ```rust
+/// use serde::de::{value::Error, Deserialize, IntoDeserializer};
+/// use serde_derive::Deserialize;
+/// use serde_spanned::{Spanned, SpannedOther, SpannedValue};
+///
+/// #[derive(Deserialize)]
+/// #[serde(rename_all = "lowercase")]
+/// enum Mode {
+///     Fast,
+///     Slow,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     mode: SpannedOther<Mode>,
+/// }
+///
+/// // what a span-aware format would produce for `{"mode": "turbo"}`
+/// let value = SpannedValue::Object(vec![(
+///     Spanned::new(String::from("mode"), 1, 7),
+///     Spanned::new(SpannedValue::from("turbo"), 9, 16),
+/// )]);
+/// let config =
+///     Config::deserialize(value.into_deserializer::<Error>()).unwrap();
+///
+/// match &config.mode {
+///     SpannedOther::Other(unknown) => {
+///         assert_eq!(unknown.value(), "turbo");
+///         assert_eq!(unknown.span(), (9, 16));
+///     },
+///     SpannedOther::Known(_) => unreachable!(),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SpannedOther<T> {
+    Known(Spanned<T>),
+    Other(Spanned<String>),
+}
+
+impl<T> SpannedOther<T> {
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            SpannedOther::Known(known) => known.span(),
+            SpannedOther::Other(other) => other.span(),
+        }
+    }
+
+    pub fn known(&self) -> Option<&T> {
+        match self {
+            SpannedOther::Known(known) => Some(known.value()),
+            SpannedOther::Other(_) => None,
+        }
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for SpannedOther<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let tag = Spanned::<String>::deserialize(de)?;
+        let (start, end) = tag.span();
+        let known: Result<T, serde::de::value::Error> =
+            T::deserialize(tag.value().as_str().into_deserializer());
+
+        match known {
+            Ok(known) => {
+                Ok(SpannedOther::Known(Spanned::new(known, start, end)))
+            },
+            Err(_) => Ok(SpannedOther::Other(tag)),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for SpannedOther<T> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SpannedOther::Known(known) => known.serialize(ser),
+            SpannedOther::Other(other) => other.serialize(ser),
+        }
+    }
+}
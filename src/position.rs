@@ -0,0 +1,344 @@
+use core::fmt::{self, Display, Formatter};
+
+/// A line and column in some source text.
+///
+/// Both numbers are zero-based and the column is counted in `char`s (or
+/// another unit, see [`Columns`]), but [`Display`] prints them one-based
+/// (e.g. `3:9`) like most editors and compilers. The `\r` of a `\r\n` line
+/// ending counts as part of the line ending rather than as a column.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub const fn new(line: usize, column: usize) -> Self {
+        Position { line, column }
+    }
+
+    /// Find the [`Position`] of a byte offset in `src`.
+    ///
+    /// Offsets past the end of `src` are treated as the end of `src`, and
+    /// offsets in the middle of a character are rounded down to the start of
+    /// that character.
+    pub fn from_offset(src: &str, offset: usize) -> Self {
+        Position::from_offset_with_columns(src, offset, Columns::Chars)
+    }
+
+    /// Find the [`Position`] of a byte offset in `src`, counting columns in
+    /// the given unit.
+    pub fn from_offset_with_columns(
+        src: &str,
+        offset: usize,
+        columns: Columns,
+    ) -> Self {
+        let mut offset = offset.min(src.len());
+        while !src.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &src[..offset];
+        let line_start = before.rfind('\n').map_or(0, |ix| ix + 1);
+
+        Position {
+            line: before.matches('\n').count(),
+            column: column(&src[line_start..], offset - line_start, columns),
+        }
+    }
+}
+
+/// How columns in a [`Position`] are counted.
+///
+/// More variants may be added by optional features (e.g.
+/// `unicode-segmentation`), so this enum is `#[non_exhaustive]`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Columns {
+    /// Count Unicode scalar values (`char`s), like most editors and
+    /// language servers.
+    Chars,
+    /// Count extended grapheme clusters, so an emoji or a letter with
+    /// combining accents is a single column. This matches what users see
+    /// in a terminal more closely.
+    #[cfg(feature = "unicode-segmentation")]
+    Graphemes,
+}
+
+impl Columns {
+    /// Count the columns in the first `len` bytes of `text`. A column which
+    /// is only partially inside `len` isn't counted.
+    fn count(self, text: &str, len: usize) -> usize {
+        match self {
+            Columns::Chars => text[..len].chars().count(),
+            #[cfg(feature = "unicode-segmentation")]
+            Columns::Graphemes => {
+                unicode_segmentation::UnicodeSegmentation::grapheme_indices(
+                    text, true,
+                )
+                .take_while(|&(ix, g)| ix + g.len() <= len)
+                .count()
+            },
+        }
+    }
+
+    /// The byte offset of the `n`'th column in `line`, where the column just
+    /// after the last character is `line.len()`.
+    #[cfg(feature = "alloc")]
+    fn nth(self, line: &str, n: usize) -> Option<usize> {
+        let end = core::iter::once(line.len());
+
+        match self {
+            Columns::Chars => {
+                line.char_indices().map(|(ix, _)| ix).chain(end).nth(n)
+            },
+            #[cfg(feature = "unicode-segmentation")]
+            Columns::Graphemes => {
+                unicode_segmentation::UnicodeSegmentation::grapheme_indices(
+                    line, true,
+                )
+                .map(|(ix, _)| ix)
+                .chain(end)
+                .nth(n)
+            },
+        }
+    }
+}
+
+impl Default for Columns {
+    fn default() -> Self { Columns::Chars }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.column + 1)
+    }
+}
+
+/// A location using the same conventions as `proc_macro2::LineColumn`.
+///
+/// The line is one-based and the column is zero-based, counted in `char`s.
+/// This is handy for build scripts and code generators which want to report
+/// errors in a config file the same way they report errors in Rust code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<Position> for LineColumn {
+    fn from(position: Position) -> Self {
+        LineColumn {
+            line: position.line + 1,
+            column: position.column,
+        }
+    }
+}
+
+impl From<LineColumn> for Position {
+    /// Convert back to a [`Position`], treating line `0` (which
+    /// `proc_macro2` never produces) as the first line.
+    fn from(line_column: LineColumn) -> Self {
+        Position {
+            line: line_column.line.saturating_sub(1),
+            column: line_column.column,
+        }
+    }
+}
+
+/// A lookup table for converting between byte offsets and [`Position`]s in
+/// the same source text.
+///
+/// [`Position::from_offset()`] has to scan everything before the offset, so
+/// when converting lots of offsets it is much cheaper to build an index of
+/// where each line starts once and binary search it.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndex<'a> {
+    src: &'a str,
+    /// The offset each line starts at. The first line always starts at 0.
+    line_starts: alloc::vec::Vec<usize>,
+    columns: Columns,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> LineIndex<'a> {
+    pub fn new(src: &'a str) -> Self {
+        let mut line_starts = alloc::vec![0];
+        newlines(src.as_bytes(), |ix| line_starts.push(ix + 1));
+
+        LineIndex {
+            src,
+            line_starts,
+            columns: Columns::Chars,
+        }
+    }
+
+    /// Count columns in a different unit.
+    pub fn with_columns(self, columns: Columns) -> Self {
+        LineIndex { columns, ..self }
+    }
+
+    pub fn columns(&self) -> Columns { self.columns }
+
+    pub fn src(&self) -> &'a str { self.src }
+
+    pub fn line_count(&self) -> usize { self.line_starts.len() }
+
+    /// Find the [`Position`] of a byte offset, with the same rounding as
+    /// [`Position::from_offset()`].
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.src.len());
+        while !self.src.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line];
+
+        Position {
+            line,
+            column: column(
+                &self.src[line_start..],
+                offset - line_start,
+                self.columns,
+            ),
+        }
+    }
+
+    /// Find the byte offset of a [`Position`], if it is inside the text.
+    ///
+    /// The position just after the last character on a line is valid.
+    pub fn offset(&self, position: Position) -> Option<usize> {
+        let start = *self.line_starts.get(position.line)?;
+        let line = match self.line_starts.get(position.line + 1) {
+            Some(next_line) => {
+                let line = &self.src[start..next_line - 1];
+                line.strip_suffix('\r').unwrap_or(line)
+            },
+            None => &self.src[start..],
+        };
+
+        self.columns.nth(line, position.column).map(|ix| start + ix)
+    }
+}
+
+/// Count the columns in the first `len` bytes of `line`, where `line` is the
+/// text from the start of a line to the end of the document. An offset
+/// between the `\r` and `\n` of a `\r\n` is treated as being at the `\r`.
+fn column(line: &str, len: usize, columns: Columns) -> usize {
+    let (before, after) = line.split_at(len);
+    let len = if after.starts_with('\n') && before.ends_with('\r') {
+        len - 1
+    } else {
+        len
+    };
+
+    columns.count(line, len)
+}
+
+/// Text with every `\r\n` replaced by `\n`, remembering where each `\r` was
+/// removed so spans into the normalized text can be mapped back to the
+/// original.
+///
+/// ```rust
+/// use serde_spanned::{NormalizedNewlines, Span};
+///
+/// let src = "[server]\r\nport = 8080\r\n";
+/// let normalized = NormalizedNewlines::new(src);
+/// assert_eq!(normalized.as_str(), "[server]\nport = 8080\n");
+///
+/// let port = normalized.original_span(Span::new(16, 20));
+/// assert_eq!(&src[port.as_range()], "8080");
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedNewlines {
+    text: alloc::string::String,
+    /// The offset of each `\n` in `text` which had a `\r` removed before it.
+    removed: alloc::vec::Vec<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl NormalizedNewlines {
+    pub fn new(src: &str) -> Self {
+        let mut text = alloc::string::String::with_capacity(src.len());
+        let mut removed = alloc::vec::Vec::new();
+        let mut rest = src;
+
+        while let Some(ix) = rest.find("\r\n") {
+            text.push_str(&rest[..ix]);
+            removed.push(text.len());
+            text.push('\n');
+            rest = &rest[ix + 2..];
+        }
+        text.push_str(rest);
+
+        NormalizedNewlines { text, removed }
+    }
+
+    pub fn as_str(&self) -> &str { &self.text }
+
+    pub fn into_string(self) -> alloc::string::String { self.text }
+
+    /// Map an offset in the normalized text back to the original text.
+    ///
+    /// An offset pointing at a `\n` maps to the `\r` before it, so a span
+    /// ending at a line ending doesn't include the `\r`.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let removed_before = match self.removed.binary_search(&offset) {
+            Ok(ix) | Err(ix) => ix,
+        };
+
+        offset + removed_before
+    }
+
+    /// Map a span in the normalized text back to the original text.
+    pub fn original_span(&self, span: crate::Span) -> crate::Span {
+        crate::Span::new(
+            self.original_offset(span.start()),
+            self.original_offset(span.end()),
+        )
+    }
+}
+
+/// Call `found` with the index of every `\n` in `haystack`.
+///
+/// This checks a whole word at a time using the same bit tricks as
+/// `memchr`'s portable fallback, only looking at individual bytes when a word
+/// contains a newline.
+#[cfg(feature = "alloc")]
+fn newlines(haystack: &[u8], mut found: impl FnMut(usize)) {
+    use core::{convert::TryInto, mem::size_of};
+
+    const LO: usize = usize::MAX / 0xFF;
+    const HI: usize = LO << 7;
+    const NEWLINES: usize = LO * b'\n' as usize;
+
+    let chunks = haystack.chunks_exact(size_of::<usize>());
+    let remainder_start = haystack.len() - chunks.remainder().len();
+
+    for (i, chunk) in chunks.enumerate() {
+        let word = usize::from_ne_bytes(chunk.try_into().unwrap());
+        let xor = word ^ NEWLINES;
+
+        // A byte in xor is zero wherever the chunk had a newline
+        if xor.wrapping_sub(LO) & !xor & HI != 0 {
+            let offset = i * size_of::<usize>();
+            for (j, &byte) in chunk.iter().enumerate() {
+                if byte == b'\n' {
+                    found(offset + j);
+                }
+            }
+        }
+    }
+
+    for (i, &byte) in haystack[remainder_start..].iter().enumerate() {
+        if byte == b'\n' {
+            found(remainder_start + i);
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{
+    walk::{Path, Segment},
+    Secret, Span, Spanned, SpannedFromStr, TotalOrd, Unspanned,
+};
+
+/// The inverse of [`Unspanned`], rebuilding a spanned value from its plain
+/// equivalent.
+///
+/// Each [`Spanned`] value is given the span `spans` returns for its path,
+/// using the same paths as [`WalkSpans`](crate::WalkSpans), or an empty
+/// `0..0` span if there isn't one. This lets transformations work on plain
+/// data while diagnostics still point at the original source.
+///
+/// This can be derived with `#[derive(Respan)]` when the `derive` feature
+/// is enabled, and structs generated by `#[derive(SpannedFields)]` implement
+/// it automatically.
+pub trait Respan: Unspanned + Sized {
+    fn respan(
+        plain: Self::Plain,
+        path: &Path<'_>,
+        spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+    ) -> Self;
+}
+
+impl<T: Respan> Respan for Spanned<T> {
+    fn respan(
+        plain: Self::Plain,
+        path: &Path<'_>,
+        spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+    ) -> Self {
+        let span = spans(path).unwrap_or_default();
+        Spanned::from_span(T::respan(plain, path, spans), span)
+    }
+}
+
+impl<T: Respan> Respan for Option<T> {
+    fn respan(
+        plain: Self::Plain,
+        path: &Path<'_>,
+        spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+    ) -> Self {
+        plain.map(|value| T::respan(value, path, spans))
+    }
+}
+
+impl<T: Respan> Respan for Secret<T> {
+    fn respan(
+        plain: Self::Plain,
+        path: &Path<'_>,
+        spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+    ) -> Self {
+        Secret::new(T::respan(plain.into_inner(), path, spans))
+    }
+}
+
+impl<T> Respan for SpannedFromStr<T> {
+    fn respan(
+        plain: Self::Plain,
+        path: &Path<'_>,
+        spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+    ) -> Self {
+        let span = spans(path).unwrap_or_default();
+        SpannedFromStr(Spanned::from_span(plain, span))
+    }
+}
+
+impl Respan for &str {
+    fn respan(
+        plain: Self::Plain,
+        _path: &Path<'_>,
+        _spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+    ) -> Self {
+        plain
+    }
+}
+
+macro_rules! leaves {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Respan for $ty {
+                fn respan(
+                    plain: Self::Plain,
+                    _path: &Path<'_>,
+                    _spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+                ) -> Self {
+                    plain
+                }
+            }
+        )*
+    };
+}
+
+leaves!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    TotalOrd<f32>,
+    TotalOrd<f64>,
+);
+
+macro_rules! tuples {
+    ($( ($($index:tt $name:ident),+) )*) => {
+        $(
+            impl<$($name: Respan),+> Respan for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn respan(
+                    plain: Self::Plain,
+                    path: &Path<'_>,
+                    spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+                ) -> Self {
+                    let ($($name,)+) = plain;
+                    ($(
+                        $name::respan(
+                            $name,
+                            &path.join(Segment::Index($index)),
+                            spans,
+                        ),
+                    )+)
+                }
+            }
+        )*
+    };
+}
+
+tuples! {
+    (0 A)
+    (0 A, 1 B)
+    (0 A, 1 B, 2 C)
+    (0 A, 1 B, 2 C, 3 D)
+    (0 A, 1 B, 2 C, 3 D, 4 E)
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F)
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G)
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H)
+}
+
+/// The spans recorded from a value, keyed by their path.
+///
+/// ```rust
+/// use serde_spanned::{Spanned, SpanMap, Unspanned};
+///
+/// let original = vec![Spanned::new(8080_u16, 16, 20)];
+/// let spans = SpanMap::record(&original);
+///
+/// let ports: Vec<u16> = original.into_plain();
+/// let ports = ports.into_iter().map(|port| port + 1).collect();
+///
+/// let respanned: Vec<Spanned<u16>> = spans.respan(ports);
+/// assert_eq!(respanned[0].span(), (16, 20));
+/// ```
+///
+/// Keys inside maps aren't [walked](crate::WalkSpans#map-keys), so they are
+/// always given empty spans.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpanMap {
+    // Keyed by segment rather than the displayed path, so a key like `a.b`
+    // isn't mixed up with the field `b` inside `a`
+    spans: alloc::collections::BTreeMap<
+        alloc::vec::Vec<crate::walk::OwnedSegment>,
+        Span,
+    >,
+}
+
+#[cfg(feature = "alloc")]
+impl SpanMap {
+    pub fn new() -> Self { SpanMap::default() }
+
+    /// Record the span of every [`Spanned`] value inside `value`.
+    ///
+    /// When several spans share a path (e.g. `Spanned<Spanned<T>>`), the
+    /// outermost one is kept.
+    pub fn record<W: crate::WalkSpans + ?Sized>(value: &W) -> Self {
+        let mut map = SpanMap::new();
+        value.walk_spans(&Path::ROOT, &mut |path, (start, end)| {
+            map.spans
+                .entry(path.owned_segments())
+                .or_insert_with(|| Span::new(start, end));
+        });
+
+        map
+    }
+
+    pub fn get(&self, path: &Path<'_>) -> Option<Span> {
+        self.spans.get(&path.owned_segments()).copied()
+    }
+
+    pub fn insert(&mut self, path: &Path<'_>, span: Span) -> Option<Span> {
+        self.spans.insert(path.owned_segments(), span)
+    }
+
+    pub fn len(&self) -> usize { self.spans.len() }
+
+    pub fn is_empty(&self) -> bool { self.spans.is_empty() }
+
+    /// Rebuild a spanned value from its plain equivalent, using the spans
+    /// in this map.
+    pub fn respan<T: Respan>(&self, plain: T::Plain) -> T {
+        T::respan(plain, &Path::ROOT, &mut |path| self.get(path))
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc_impls {
+    use super::Respan;
+    use crate::{
+        walk::{Path, Segment},
+        Span,
+    };
+    use alloc::{
+        borrow::Cow,
+        boxed::Box,
+        collections::{BTreeMap, VecDeque},
+        string::String,
+        vec::Vec,
+    };
+    use core::fmt::Display;
+
+    leaves!(String, Cow<'_, str>);
+
+    impl<T: Respan> Respan for Box<T> {
+        fn respan(
+            plain: Self::Plain,
+            path: &Path<'_>,
+            spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+        ) -> Self {
+            Box::new(T::respan(*plain, path, spans))
+        }
+    }
+
+    impl<T: Respan> Respan for Vec<T> {
+        fn respan(
+            plain: Self::Plain,
+            path: &Path<'_>,
+            spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+        ) -> Self {
+            plain
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    T::respan(item, &path.join(Segment::Index(i)), spans)
+                })
+                .collect()
+        }
+    }
+
+    impl<T: Respan> Respan for VecDeque<T> {
+        fn respan(
+            plain: Self::Plain,
+            path: &Path<'_>,
+            spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+        ) -> Self {
+            plain
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    T::respan(item, &path.join(Segment::Index(i)), spans)
+                })
+                .collect()
+        }
+    }
+
+    impl<K, V> Respan for BTreeMap<K, V>
+    where
+        K: Respan + Ord,
+        K::Plain: Ord + Display,
+        V: Respan,
+    {
+        fn respan(
+            plain: Self::Plain,
+            path: &Path<'_>,
+            spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+        ) -> Self {
+            plain
+                .into_iter()
+                .map(|(key, value)| {
+                    let value =
+                        V::respan(value, &path.join(Segment::Key(&key)), spans);
+                    (K::respan(key, path, &mut |_| None), value)
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod std_impls {
+    use super::Respan;
+    use crate::{
+        walk::{Path, Segment},
+        Span,
+    };
+    use core::{
+        fmt::Display,
+        hash::{BuildHasher, Hash},
+    };
+    use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+    leaves!(OsString, PathBuf);
+
+    impl<K, V, S> Respan for HashMap<K, V, S>
+    where
+        K: Respan + Eq + Hash,
+        K::Plain: Eq + Hash + Display,
+        V: Respan,
+        S: BuildHasher + Default,
+    {
+        fn respan(
+            plain: Self::Plain,
+            path: &Path<'_>,
+            spans: &mut dyn FnMut(&Path<'_>) -> Option<Span>,
+        ) -> Self {
+            plain
+                .into_iter()
+                .map(|(key, value)| {
+                    let value =
+                        V::respan(value, &path.join(Segment::Key(&key)), spans);
+                    (K::respan(key, path, &mut |_| None), value)
+                })
+                .collect()
+        }
+    }
+}
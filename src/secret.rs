@@ -0,0 +1,61 @@
+use core::fmt::{self, Debug, Formatter};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A value which should never be shown to the user, such as a password.
+///
+/// The wrapped value is masked in `Debug` output, so a
+/// `Spanned<Secret<String>>` can still be used to point at *where* the
+/// password was written without ever printing it. [`Excerpt`] and
+/// [`Snapshot`] print `<redacted>` instead of the text the secret's spans
+/// cover.
+///
+/// [`Excerpt`]: crate::snapshot::Excerpt
+/// [`Snapshot`]: crate::snapshot::Snapshot
+///
+/// (De)serialization is transparent.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// The placeholder printed instead of the secret value.
+    pub const REDACTED: &'static str = "<redacted>";
+
+    pub const fn new(value: T) -> Self { Secret(value) }
+
+    /// Get a reference to the secret value.
+    pub const fn expose(&self) -> &T { &self.0 }
+
+    pub fn expose_mut(&mut self) -> &mut T { &mut self.0 }
+
+    pub fn into_inner(self) -> T { self.0 }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self { Secret::new(value) }
+}
+
+impl<T> Debug for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Secret").field(&Redacted).finish()
+    }
+}
+
+struct Redacted;
+
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(Secret::<()>::REDACTED)
+    }
+}
+
+impl<T: Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(ser)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        T::deserialize(de).map(Secret)
+    }
+}
@@ -0,0 +1,203 @@
+//! Stable, human-readable renderings of [`Spanned`] values for snapshot
+//! tests.
+//!
+//! Raw byte offsets are hard to review, so these types resolve each span
+//! against the original source and print the text it covers along with its
+//! line and column.
+//!
+//! ```rust
+//! use serde_spanned::{snapshot::Excerpt, Spanned};
+//!
+//! let src = "[server]\nport = 8080\n";
+//! let port = Spanned::new(8080_u16, 16, 20);
+//!
+//! assert_eq!(
+//!     Excerpt::new(&port, src).named("config.toml").to_string(),
+//!     r#""8080" (config.toml 2:8..2:12) -> 8080"#,
+//! );
+//! ```
+//!
+//! [`Spanned`]: crate::Spanned
+
+use crate::{
+    walk::{Path, WalkSpans},
+    Columns, Position, Secret, Spanned,
+};
+use core::fmt::{self, Debug, Display, Formatter};
+
+/// The longest excerpt printed before it gets truncated.
+const MAX_EXCERPT_CHARS: usize = 40;
+
+/// Render a single [`Spanned`] value as `"text" (name 1:2..1:5) -> value`.
+#[derive(Debug, Copy, Clone)]
+pub struct Excerpt<'a, T> {
+    spanned: &'a Spanned<T>,
+    src: &'a str,
+    name: Option<&'a str>,
+    columns: Columns,
+    redacted: bool,
+}
+
+impl<'a, T> Excerpt<'a, T> {
+    pub const fn new(spanned: &'a Spanned<T>, src: &'a str) -> Self {
+        Excerpt {
+            spanned,
+            src,
+            name: None,
+            columns: Columns::Chars,
+            redacted: false,
+        }
+    }
+
+    /// Include the name of the source file in the output.
+    pub const fn named(self, name: &'a str) -> Self {
+        Excerpt {
+            name: Some(name),
+            ..self
+        }
+    }
+
+    /// Count columns in a different unit.
+    pub const fn with_columns(self, columns: Columns) -> Self {
+        Excerpt { columns, ..self }
+    }
+
+    /// Print `<redacted>` instead of the text the span covers.
+    ///
+    /// This happens automatically for [`Secret`](crate::Secret) values.
+    pub const fn redacted(self) -> Self {
+        Excerpt {
+            redacted: true,
+            ..self
+        }
+    }
+}
+
+impl<T: Debug + WalkSpans> Display for Excerpt<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_location(
+            f,
+            self.src,
+            self.name,
+            self.columns,
+            self.redacted || self.spanned.value().is_secret(),
+            self.spanned.span(),
+        )?;
+        write!(f, " -> {:?}", self.spanned.value())
+    }
+}
+
+/// Render every span inside a [`WalkSpans`] value, one per line.
+///
+/// Each line looks like `server.port: "8080" (config.toml 3:9..3:13)`, with
+/// spans listed in the order they are walked. The text of spans inside a
+/// [`Secret`](crate::Secret) is printed as `<redacted>`.
+#[derive(Copy, Clone)]
+pub struct Snapshot<'a, T: ?Sized> {
+    value: &'a T,
+    src: &'a str,
+    name: Option<&'a str>,
+    columns: Columns,
+}
+
+impl<'a, T: ?Sized> Snapshot<'a, T> {
+    pub const fn new(value: &'a T, src: &'a str) -> Self {
+        Snapshot {
+            value,
+            src,
+            name: None,
+            columns: Columns::Chars,
+        }
+    }
+
+    /// Include the name of the source file in the output.
+    pub const fn named(self, name: &'a str) -> Self {
+        Snapshot {
+            name: Some(name),
+            ..self
+        }
+    }
+
+    /// Count columns in a different unit.
+    pub const fn with_columns(self, columns: Columns) -> Self {
+        Snapshot { columns, ..self }
+    }
+}
+
+impl<T: WalkSpans + ?Sized> Display for Snapshot<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut result = Ok(());
+
+        self.value.walk_spans(&Path::ROOT, &mut |path, span| {
+            if result.is_err() {
+                return;
+            }
+
+            result = if path.is_root() {
+                f.write_str("<root>: ")
+            } else {
+                write!(f, "{}: ", path)
+            }
+            .and_then(|_| {
+                write_location(
+                    f,
+                    self.src,
+                    self.name,
+                    self.columns,
+                    path.is_redacted(),
+                    span,
+                )
+            })
+            .and_then(|_| f.write_str("\n"));
+        });
+
+        result
+    }
+}
+
+impl<T: ?Sized> Debug for Snapshot<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("src", &self.src)
+            .field("name", &self.name)
+            .field("columns", &self.columns)
+            .finish()
+    }
+}
+
+/// Write `"text" (name 1:2..1:5)`.
+fn write_location(
+    f: &mut Formatter<'_>,
+    src: &str,
+    name: Option<&str>,
+    columns: Columns,
+    redacted: bool,
+    (start, end): (usize, usize),
+) -> fmt::Result {
+    match src.get(start..end) {
+        Some(_) if redacted => f.write_str(Secret::<()>::REDACTED)?,
+        Some(text) => write_excerpt(f, text)?,
+        None => f.write_str("<invalid span>")?,
+    }
+
+    f.write_str(" (")?;
+    if let Some(name) = name {
+        write!(f, "{} ", name)?;
+    }
+
+    write!(
+        f,
+        "{}..{})",
+        Position::from_offset_with_columns(src, start, columns),
+        Position::from_offset_with_columns(src, end, columns)
+    )
+}
+
+/// Write the text as an escaped string, truncating anything too long to
+/// review comfortably.
+fn write_excerpt(f: &mut Formatter<'_>, text: &str) -> fmt::Result {
+    match text.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((ix, _)) => write!(f, "{:?}...", &text[..ix]),
+        None => write!(f, "{:?}", text),
+    }
+}
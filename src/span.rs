@@ -0,0 +1,266 @@
+use core::{
+    fmt::{self, Debug, Display, Formatter},
+    num::NonZeroUsize,
+    ops::Range,
+};
+
+/// The start and end byte offsets of a value in its source text.
+///
+/// Internally the end is stored as `end + 1` in a [`NonZeroUsize`], leaving
+/// a niche so `Option<Span>` and `Option<Spanned<T>>` are no bigger than
+/// `Span` and `Spanned<T>`. Offsets index into a `str`, which can never be
+/// longer than `isize::MAX` bytes, so an end of `usize::MAX` is clamped to
+/// `usize::MAX - 1`.
+///
+/// Everything here is a `const fn`, so spans can be built and combined in
+/// static tables.
+///
+/// ```rust
+/// use serde_spanned::Span;
+///
+/// const HEADER: Span = Span::new(0, 8);
+/// const BODY: Span = Span::new(9, 42).shifted(HEADER.len());
+/// const DOCUMENT: Span = HEADER.union(BODY);
+///
+/// assert_eq!(BODY, Span::new(17, 50));
+/// assert_eq!(DOCUMENT, Span::new(0, 50));
+/// assert!(DOCUMENT.contains_span(BODY));
+/// assert!(!HEADER.contains(HEADER.end()));
+/// ```
+///
+/// [`Spanned<T>`]: crate::Spanned
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    start: usize,
+    end_plus_one: NonZeroUsize,
+}
+
+impl Span {
+    /// Create a span without checking that `start <= end`.
+    ///
+    /// Methods like [`Span::len()`] assume the span is valid, so prefer
+    /// [`Span::try_new()`] for offsets which come from untrusted input.
+    pub const fn new(start: usize, end: usize) -> Self {
+        let end_plus_one = if end == usize::MAX { end } else { end + 1 };
+
+        Span {
+            start,
+            // Safety: end_plus_one is at least 1, even when end is 0
+            end_plus_one: unsafe { NonZeroUsize::new_unchecked(end_plus_one) },
+        }
+    }
+
+    /// Create a span, checking that it doesn't end before it starts.
+    ///
+    /// The check happens after an end of `usize::MAX` is clamped, so a span
+    /// starting at `usize::MAX` is rejected too.
+    ///
+    /// ```rust
+    /// use serde_spanned::Span;
+    ///
+    /// assert_eq!(Span::try_new(2, 5), Ok(Span::new(2, 5)));
+    /// assert!(Span::try_new(10, 5).is_err());
+    /// assert!(Span::try_new(usize::MAX, usize::MAX).is_err());
+    /// ```
+    pub const fn try_new(
+        start: usize,
+        end: usize,
+    ) -> Result<Self, InvalidSpan> {
+        let span = Span::new(start, end);
+
+        if start <= span.end() {
+            Ok(span)
+        } else {
+            Err(InvalidSpan {
+                start,
+                end: span.end(),
+            })
+        }
+    }
+
+    pub const fn start(self) -> usize { self.start }
+
+    pub const fn end(self) -> usize { self.end_plus_one.get() - 1 }
+
+    pub const fn len(self) -> usize { self.end() - self.start() }
+
+    pub const fn is_empty(self) -> bool { self.len() == 0 }
+
+    pub const fn as_tuple(self) -> (usize, usize) { (self.start(), self.end()) }
+
+    pub const fn as_range(self) -> Range<usize> { self.start()..self.end() }
+
+    /// Move the span forwards by `offset` bytes (e.g. to make a span relative
+    /// to a sub-document relative to the whole document).
+    pub const fn shifted(self, offset: usize) -> Self {
+        Span::new(self.start() + offset, self.end() + offset)
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub const fn union(self, other: Span) -> Self {
+        let start = if self.start() < other.start() {
+            self.start()
+        } else {
+            other.start()
+        };
+        let end = if self.end() > other.end() {
+            self.end()
+        } else {
+            other.end()
+        };
+
+        Span::new(start, end)
+    }
+
+    /// Is `offset` inside this span? The end is exclusive.
+    pub const fn contains(self, offset: usize) -> bool {
+        self.start() <= offset && offset < self.end()
+    }
+
+    /// Is `other` entirely inside this span?
+    pub const fn contains_span(self, other: Span) -> bool {
+        self.start() <= other.start() && other.end() <= self.end()
+    }
+
+    /// The span of each line of `src` which this span covers, clipped to
+    /// the span and without line endings.
+    ///
+    /// This lets a diagnostic point at the right line inside a multi-line
+    /// value, such as a TOML `"""` string or a YAML block scalar. Offsets
+    /// which aren't inside `src` are ignored.
+    ///
+    /// A span ending just after a line break stops at that line, rather than
+    /// yielding an empty line after it. An empty span yields the single empty
+    /// span where it is.
+    ///
+    /// ```rust
+    /// use serde_spanned::Span;
+    ///
+    /// let src = "msg = \"\"\"\r\nhello\r\nworld\"\"\"\n";
+    /// let lines: Vec<_> = Span::new(6, 26)
+    ///     .lines(src)
+    ///     .map(|line| &src[line.as_range()])
+    ///     .collect();
+    ///
+    /// assert_eq!(lines, ["\"\"\"", "hello", "world\"\"\""]);
+    /// ```
+    pub fn lines(self, src: &str) -> SpanLines<'_> {
+        let end = self.end().min(src.len());
+
+        SpanLines {
+            src,
+            next: Some(self.start().min(end)),
+            end,
+        }
+    }
+}
+
+/// An iterator over the lines a [`Span`] covers, created by
+/// [`Span::lines()`].
+#[derive(Debug, Clone)]
+pub struct SpanLines<'a> {
+    src: &'a str,
+    next: Option<usize>,
+    end: usize,
+}
+
+impl Iterator for SpanLines<'_> {
+    type Item = Span;
+
+    fn next(&mut self) -> Option<Span> {
+        let start = self.next?;
+        let rest = &self.src.as_bytes()[start..self.end];
+
+        match rest.iter().position(|&b| b == b'\n') {
+            Some(newline) => {
+                // A span ending just after a line break doesn't cover the
+                // next line
+                let next = start + newline + 1;
+                self.next = if next < self.end { Some(next) } else { None };
+
+                let line = &rest[..newline];
+                let len = if line.ends_with(b"\r") {
+                    newline - 1
+                } else {
+                    newline
+                };
+                Some(Span::new(start, start + len))
+            },
+            None => {
+                self.next = None;
+
+                // Don't include the \r of a \r\n the span ends inside
+                let crlf = rest.ends_with(b"\r")
+                    && self.src.as_bytes().get(self.end) == Some(&b'\n');
+                let end = if crlf { self.end - 1 } else { self.end };
+                Some(Span::new(start, end))
+            },
+        }
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self { Span::new(0, 0) }
+}
+
+impl Debug for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Span")
+            .field("start", &self.start())
+            .field("end", &self.end())
+            .finish()
+    }
+}
+
+/// The error returned by [`Span::try_new()`] when a span ends before it
+/// starts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidSpan {
+    start: usize,
+    end: usize,
+}
+
+impl InvalidSpan {
+    pub const fn start(self) -> usize { self.start }
+
+    pub const fn end(self) -> usize { self.end }
+}
+
+impl Display for InvalidSpan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the span {}..{} ends before it starts",
+            self.start, self.end
+        )
+    }
+}
+
+impl serde::ser::StdError for InvalidSpan {}
+
+impl From<(usize, usize)> for Span {
+    fn from((start, end): (usize, usize)) -> Self { Span::new(start, end) }
+}
+
+impl From<Span> for (usize, usize) {
+    fn from(span: Span) -> Self { span.as_tuple() }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self { Span::new(range.start, range.end) }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self { span.as_range() }
+}
+
+// Make sure the niche is actually used. If the sizes ever differ, the array
+// lengths won't match and this fails to compile.
+const _: [(); core::mem::size_of::<Span>()] =
+    [(); core::mem::size_of::<Option<Span>>()];
+const _: [(); core::mem::size_of::<crate::Spanned<u32>>()] =
+    [(); core::mem::size_of::<Option<crate::Spanned<u32>>>()];
+const _: [(); core::mem::size_of::<crate::Spanned<u64>>()] =
+    [(); core::mem::size_of::<Option<crate::Spanned<u64>>>()];
+const _: [(); core::mem::size_of::<crate::Spanned<bool>>()] =
+    [(); core::mem::size_of::<Option<crate::Spanned<bool>>>()];
@@ -0,0 +1,98 @@
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A float with a total order, so it can be used as a map key or
+/// deduplicated.
+///
+/// Values are ordered like IEEE 754's `totalOrder` predicate (the same as
+/// `f64::total_cmp()`): negative NaNs, then negative infinity through to
+/// positive infinity with `-0.0 < +0.0`, then positive NaNs. Two values are
+/// only equal if they have the same bits.
+///
+/// Because [`Spanned`] compares by value, a `Spanned<TotalOrd<f64>>` keeps
+/// its span and can still go in a `BTreeSet`.
+///
+/// (De)serialization is transparent.
+///
+/// [`Spanned`]: crate::Spanned
+#[derive(Default, Copy, Clone)]
+pub struct TotalOrd<F>(pub F);
+
+impl<F> TotalOrd<F> {
+    pub const fn new(value: F) -> Self { TotalOrd(value) }
+
+    pub fn into_inner(self) -> F { self.0 }
+}
+
+impl<F> From<F> for TotalOrd<F> {
+    fn from(value: F) -> Self { TotalOrd(value) }
+}
+
+macro_rules! total_ord {
+    ($($float:ty => $signed:ty, $unsigned:ty, $sign_shift:expr);* $(;)?) => {
+        $(
+            impl TotalOrd<$float> {
+                /// Map the float's bits to an integer with the same order.
+                fn key(self) -> $signed {
+                    let bits = self.0.to_bits() as $signed;
+                    // Flip everything except the sign bit for negative
+                    // numbers, so larger magnitudes sort lower
+                    bits ^ (((bits >> $sign_shift) as $unsigned) >> 1) as $signed
+                }
+            }
+
+            impl PartialEq for TotalOrd<$float> {
+                fn eq(&self, other: &Self) -> bool { self.key() == other.key() }
+            }
+
+            impl Eq for TotalOrd<$float> {}
+
+            impl PartialOrd for TotalOrd<$float> {
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    Some(self.cmp(other))
+                }
+            }
+
+            impl Ord for TotalOrd<$float> {
+                fn cmp(&self, other: &Self) -> Ordering {
+                    self.key().cmp(&other.key())
+                }
+            }
+
+            impl Hash for TotalOrd<$float> {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    self.key().hash(state)
+                }
+            }
+        )*
+    };
+}
+
+total_ord! {
+    f32 => i32, u32, 31;
+    f64 => i64, u64, 63;
+}
+
+impl<F: Debug> Debug for TotalOrd<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { self.0.fmt(f) }
+}
+
+impl<F: Display> Display for TotalOrd<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { self.0.fmt(f) }
+}
+
+impl<F: Serialize> Serialize for TotalOrd<F> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(ser)
+    }
+}
+
+impl<'de, F: Deserialize<'de>> Deserialize<'de> for TotalOrd<F> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        F::deserialize(de).map(TotalOrd)
+    }
+}
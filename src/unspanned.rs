@@ -0,0 +1,234 @@
+use crate::{Secret, Spanned, SpannedFromStr, TotalOrd};
+
+/// Recursively strip the [`Spanned`] wrappers from a value.
+///
+/// This is useful when data needs to be handed to code which doesn't care
+/// about (or can't cope with) span information.
+///
+/// This can be derived with `#[derive(Unspanned)]` when the `derive` feature
+/// is enabled, and structs generated by `#[derive(SpannedFields)]` implement
+/// it automatically.
+pub trait Unspanned {
+    /// The equivalent type with all spans removed.
+    type Plain;
+
+    fn into_plain(self) -> Self::Plain;
+}
+
+impl<T: Unspanned> Unspanned for Spanned<T> {
+    type Plain = T::Plain;
+
+    fn into_plain(self) -> Self::Plain { self.into_value().into_plain() }
+}
+
+impl<T: Unspanned> Unspanned for Option<T> {
+    type Plain = Option<T::Plain>;
+
+    fn into_plain(self) -> Self::Plain { self.map(Unspanned::into_plain) }
+}
+
+impl<T: Unspanned> Unspanned for Secret<T> {
+    type Plain = Secret<T::Plain>;
+
+    fn into_plain(self) -> Self::Plain {
+        Secret::new(self.into_inner().into_plain())
+    }
+}
+
+impl<T> Unspanned for SpannedFromStr<T> {
+    type Plain = T;
+
+    fn into_plain(self) -> Self::Plain { self.0.into_value() }
+}
+
+impl<'a> Unspanned for &'a str {
+    type Plain = &'a str;
+
+    fn into_plain(self) -> Self::Plain { self }
+}
+
+macro_rules! leaves {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Unspanned for $ty {
+                type Plain = $ty;
+
+                fn into_plain(self) -> Self::Plain { self }
+            }
+        )*
+    };
+}
+
+leaves!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    TotalOrd<f32>,
+    TotalOrd<f64>,
+);
+
+macro_rules! tuples {
+    ($( ($($name:ident),+) )*) => {
+        $(
+            impl<$($name: Unspanned),+> Unspanned for ($($name,)+) {
+                type Plain = ($($name::Plain,)+);
+
+                #[allow(non_snake_case)]
+                fn into_plain(self) -> Self::Plain {
+                    let ($($name,)+) = self;
+                    ($($name.into_plain(),)+)
+                }
+            }
+        )*
+    };
+}
+
+tuples! {
+    (A)
+    (A, B)
+    (A, B, C)
+    (A, B, C, D)
+    (A, B, C, D, E)
+    (A, B, C, D, E, F)
+    (A, B, C, D, E, F, G)
+    (A, B, C, D, E, F, G, H)
+}
+
+#[cfg(feature = "alloc")]
+mod alloc_impls {
+    use super::Unspanned;
+    use alloc::{
+        borrow::Cow,
+        boxed::Box,
+        collections::{BTreeMap, BTreeSet, VecDeque},
+        string::String,
+        vec::Vec,
+    };
+
+    impl Unspanned for String {
+        type Plain = String;
+
+        fn into_plain(self) -> Self::Plain { self }
+    }
+
+    impl<'a> Unspanned for Cow<'a, str> {
+        type Plain = Cow<'a, str>;
+
+        fn into_plain(self) -> Self::Plain { self }
+    }
+
+    impl<T: Unspanned> Unspanned for Box<T> {
+        type Plain = Box<T::Plain>;
+
+        fn into_plain(self) -> Self::Plain { Box::new((*self).into_plain()) }
+    }
+
+    impl<T: Unspanned> Unspanned for Vec<T> {
+        type Plain = Vec<T::Plain>;
+
+        fn into_plain(self) -> Self::Plain {
+            self.into_iter().map(Unspanned::into_plain).collect()
+        }
+    }
+
+    impl<T: Unspanned> Unspanned for VecDeque<T> {
+        type Plain = VecDeque<T::Plain>;
+
+        fn into_plain(self) -> Self::Plain {
+            self.into_iter().map(Unspanned::into_plain).collect()
+        }
+    }
+
+    impl<T> Unspanned for BTreeSet<T>
+    where
+        T: Unspanned,
+        T::Plain: Ord,
+    {
+        type Plain = BTreeSet<T::Plain>;
+
+        fn into_plain(self) -> Self::Plain {
+            self.into_iter().map(Unspanned::into_plain).collect()
+        }
+    }
+
+    impl<K, V> Unspanned for BTreeMap<K, V>
+    where
+        K: Unspanned,
+        K::Plain: Ord,
+        V: Unspanned,
+    {
+        type Plain = BTreeMap<K::Plain, V::Plain>;
+
+        fn into_plain(self) -> Self::Plain {
+            self.into_iter()
+                .map(|(k, v)| (k.into_plain(), v.into_plain()))
+                .collect()
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod std_impls {
+    use super::Unspanned;
+    use core::hash::{BuildHasher, Hash};
+    use std::{
+        collections::{HashMap, HashSet},
+        ffi::OsString,
+        path::PathBuf,
+    };
+
+    impl Unspanned for PathBuf {
+        type Plain = PathBuf;
+
+        fn into_plain(self) -> Self::Plain { self }
+    }
+
+    impl Unspanned for OsString {
+        type Plain = OsString;
+
+        fn into_plain(self) -> Self::Plain { self }
+    }
+
+    impl<T, S> Unspanned for HashSet<T, S>
+    where
+        T: Unspanned,
+        T::Plain: Eq + Hash,
+        S: BuildHasher + Default,
+    {
+        type Plain = HashSet<T::Plain, S>;
+
+        fn into_plain(self) -> Self::Plain {
+            self.into_iter().map(Unspanned::into_plain).collect()
+        }
+    }
+
+    impl<K, V, S> Unspanned for HashMap<K, V, S>
+    where
+        K: Unspanned,
+        K::Plain: Eq + Hash,
+        V: Unspanned,
+        S: BuildHasher + Default,
+    {
+        type Plain = HashMap<K::Plain, V::Plain, S>;
+
+        fn into_plain(self) -> Self::Plain {
+            self.into_iter()
+                .map(|(k, v)| (k.into_plain(), v.into_plain()))
+                .collect()
+        }
+    }
+}
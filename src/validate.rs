@@ -0,0 +1,105 @@
+use crate::{Position, Span};
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+
+/// An error found while validating a deserialized value, pointing at the
+/// span which broke the rule and any other spans involved.
+///
+/// This is usually created with [`ensure!()`](crate::ensure).
+///
+/// ```rust
+/// use serde_spanned::{ensure, Spanned, ValidationError};
+///
+/// fn check(
+///     min: &Spanned<u32>,
+///     max: &Spanned<u32>,
+/// ) -> Result<(), ValidationError> {
+///     ensure!(min <= max, min.span(), [max.span()], "min must be <= max");
+///     Ok(())
+/// }
+///
+/// let src = "min = 10\nmax = 5\n";
+/// let err = check(&Spanned::new(10, 6, 8), &Spanned::new(5, 15, 16))
+///     .unwrap_err();
+///
+/// assert_eq!(
+///     err.located(src).to_string(),
+///     "min must be <= max (1:7..1:9)\n  related: 2:7..2:8",
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationError {
+    pub message: String,
+    pub primary: Span,
+    pub related: Vec<Span>,
+}
+
+impl ValidationError {
+    pub fn new(message: impl Into<String>, primary: impl Into<Span>) -> Self {
+        ValidationError {
+            message: message.into(),
+            primary: primary.into(),
+            related: Vec::new(),
+        }
+    }
+
+    /// Point at another span involved in the error.
+    pub fn with_related(mut self, span: impl Into<Span>) -> Self {
+        self.related.push(span.into());
+        self
+    }
+
+    /// Display the error with line and column numbers from `src`.
+    pub fn located<'a>(&'a self, src: &'a str) -> Located<'a> {
+        Located { error: self, src }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at {}..{})",
+            self.message,
+            self.primary.start(),
+            self.primary.end()
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValidationError {}
+
+/// A [`ValidationError`] displayed with line and column numbers, one span
+/// per line.
+#[derive(Debug, Copy, Clone)]
+pub struct Located<'a> {
+    error: &'a ValidationError,
+    src: &'a str,
+}
+
+impl Located<'_> {
+    fn write_span(&self, f: &mut Formatter<'_>, span: Span) -> fmt::Result {
+        write!(
+            f,
+            "{}..{}",
+            Position::from_offset(self.src, span.start()),
+            Position::from_offset(self.src, span.end())
+        )
+    }
+}
+
+impl Display for Located<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (", self.error.message)?;
+        self.write_span(f, self.error.primary)?;
+        f.write_str(")")?;
+
+        for &span in &self.error.related {
+            f.write_str("\n  related: ")?;
+            self.write_span(f, span)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1384 @@
+//! A dynamically typed value where every node has a span.
+
+use crate::{
+    de::SpannedDeserializer,
+    walk::{Path, Segment},
+    Span, Spanned, SpannedSeed, Unspanned,
+};
+use alloc::{
+    borrow::Cow,
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cell::Cell,
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+    mem,
+};
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeSeed, EnumAccess, Error, IgnoredAny, IntoDeserializer,
+        MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer, Serialize,
+    Serializer,
+};
+
+/// Any value, with the span of every array element, map key, and map value.
+///
+/// This is useful for tools like linters which need to walk a document
+/// without knowing its schema. Deserialize a `Spanned<SpannedValue>` to
+/// also get the span of the whole document.
+///
+/// Object entries are kept in the order they appear in the document, and a
+/// key which appears twice is an error. Use [`Unspanned::into_plain()`] to
+/// get the equivalent [`Value`] without any spans.
+///
+/// Deserializing applies the default [`Limits`], which only limit nesting.
+/// Use [`SpannedValue::deserialize_with_limits()`] for documents from
+/// untrusted input.
+///
+/// A `SpannedValue` can be turned back into a [`Deserializer`] with
+/// [`IntoDeserializer`], so it can be deserialized into a typed struct
+/// later on. Any [`Spanned`] values inside that struct get the spans which
+/// were recorded originally.
+///
+/// ```rust
+/// use serde::de::{value::Error, Deserialize, IntoDeserializer};
+/// use serde_derive::Deserialize;
+/// use serde_spanned::{Spanned, SpannedValue};
+///
+/// // what a span-aware format would produce for `{"port": 8080}`
+/// let value = SpannedValue::Object(vec![(
+///     Spanned::new(String::from("port"), 1, 7),
+///     Spanned::new(SpannedValue::from(8080_u64), 9, 13),
+/// )]);
+///
+/// assert_eq!(value.get("port").unwrap().span(), (9, 13));
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     port: Spanned<u16>,
+/// }
+///
+/// let config =
+///     Config::deserialize(value.into_deserializer::<Error>()).unwrap();
+///
+/// assert_eq!(config.port, Spanned::new(8080, 9, 13));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedValue {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Spanned<SpannedValue>>),
+    /// The entries of a map, in document order.
+    Object(Vec<(Spanned<String>, Spanned<SpannedValue>)>),
+}
+
+impl SpannedValue {
+    pub fn is_null(&self) -> bool { matches!(self, SpannedValue::Null) }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            SpannedValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            SpannedValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            SpannedValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Spanned<SpannedValue>]> {
+        match self {
+            SpannedValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(
+        &self,
+    ) -> Option<&[(Spanned<String>, Spanned<SpannedValue>)]> {
+        match self {
+            SpannedValue::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Look up a key, if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Spanned<SpannedValue>> {
+        self.get_entry(key).map(|(_, value)| value)
+    }
+
+    /// Look up a key, if this is an object, returning the key as well so
+    /// its span is available.
+    pub fn get_entry(
+        &self,
+        key: &str,
+    ) -> Option<&(Spanned<String>, Spanned<SpannedValue>)> {
+        self.as_object()?.iter().find(|(k, _)| k.value() == key)
+    }
+
+    /// The number of values in the tree, including this one. Object keys
+    /// aren't counted.
+    pub fn node_count(&self) -> usize {
+        match self {
+            SpannedValue::Array(items) => {
+                1 + items
+                    .iter()
+                    .map(|item| item.value().node_count())
+                    .sum::<usize>()
+            },
+            SpannedValue::Object(entries) => {
+                1 + entries
+                    .iter()
+                    .map(|(_, value)| value.value().node_count())
+                    .sum::<usize>()
+            },
+            _ => 1,
+        }
+    }
+
+    /// The number of bytes of heap memory owned by the tree.
+    ///
+    /// This is the capacity of every string and vector, so it doesn't
+    /// include the allocator's own overhead or the `SpannedValue` itself.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            SpannedValue::String(s) => s.capacity(),
+            SpannedValue::Array(items) => {
+                items.capacity() * mem::size_of::<Spanned<SpannedValue>>()
+                    + items
+                        .iter()
+                        .map(|item| item.value().heap_size())
+                        .sum::<usize>()
+            },
+            SpannedValue::Object(entries) => {
+                entries.capacity()
+                    * mem::size_of::<(Spanned<String>, Spanned<SpannedValue>)>()
+                    + entries
+                        .iter()
+                        .map(|(key, value)| {
+                            key.value().capacity() + value.value().heap_size()
+                        })
+                        .sum::<usize>()
+            },
+            _ => 0,
+        }
+    }
+
+    /// Look up an array element, if this is an array.
+    pub fn get_index(&self, index: usize) -> Option<&Spanned<SpannedValue>> {
+        self.as_array()?.get(index)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Spanned<SpannedValue>> {
+        match self {
+            SpannedValue::Object(entries) => entries
+                .iter_mut()
+                .find(|(k, _)| k.value() == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn get_index_mut(
+        &mut self,
+        index: usize,
+    ) -> Option<&mut Spanned<SpannedValue>> {
+        match self {
+            SpannedValue::Array(items) => items.get_mut(index),
+            _ => None,
+        }
+    }
+
+    /// Look up a nested value using a path like `server.ports[0]`, in the
+    /// same format [`Path`] is displayed in.
+    ///
+    /// Keys containing `.` or `[` can't be written this way, so use
+    /// [`SpannedValue::pointer()`] for those.
+    pub fn get_path(&self, path: &str) -> Option<&Spanned<SpannedValue>> {
+        let mut steps = parse_path(path)?.into_iter();
+        let mut current = steps.next()?.get(self)?;
+
+        for step in steps {
+            current = step.get(current.value())?;
+        }
+
+        Some(current)
+    }
+
+    pub fn get_path_mut(
+        &mut self,
+        path: &str,
+    ) -> Option<&mut Spanned<SpannedValue>> {
+        let mut steps = parse_path(path)?.into_iter();
+        let mut current = steps.next()?.get_mut(self)?;
+
+        for step in steps {
+            current = step.get_mut(current.value_mut())?;
+        }
+
+        Some(current)
+    }
+
+    /// Look up a nested value using a JSON Pointer (RFC 6901), like
+    /// `/server/ports/0`.
+    ///
+    /// The empty pointer refers to this value, which has no span, so it
+    /// returns `None`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Spanned<SpannedValue>> {
+        let mut steps = parse_pointer(pointer)?.into_iter();
+        let mut current = steps.next()?.get(self)?;
+
+        for step in steps {
+            current = step.get(current.value())?;
+        }
+
+        Some(current)
+    }
+
+    pub fn pointer_mut(
+        &mut self,
+        pointer: &str,
+    ) -> Option<&mut Spanned<SpannedValue>> {
+        let mut steps = parse_pointer(pointer)?.into_iter();
+        let mut current = steps.next()?.get_mut(self)?;
+
+        for step in steps {
+            current = step.get_mut(current.value_mut())?;
+        }
+
+        Some(current)
+    }
+
+    /// The elements of an array or the values in an object.
+    pub fn iter(&self) -> impl Iterator<Item = &Spanned<SpannedValue>> {
+        let (items, entries) = match self {
+            SpannedValue::Array(items) => (&items[..], Default::default()),
+            SpannedValue::Object(entries) => (Default::default(), &entries[..]),
+            _ => Default::default(),
+        };
+
+        items.iter().chain(entries.iter().map(|(_, value)| value))
+    }
+
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut Spanned<SpannedValue>> {
+        let (items, entries) = match self {
+            SpannedValue::Array(items) => (&mut items[..], Default::default()),
+            SpannedValue::Object(entries) => {
+                (Default::default(), &mut entries[..])
+            },
+            _ => Default::default(),
+        };
+
+        items
+            .iter_mut()
+            .chain(entries.iter_mut().map(|(_, value)| value))
+    }
+
+    /// Every value inside this one, depth-first and parents before their
+    /// children, along with its path.
+    pub fn walk(&self) -> Walk<'_> {
+        let mut walk = Walk { stack: Vec::new() };
+        walk.push_children("", self);
+        walk
+    }
+
+    /// Every value inside this one which isn't an array or object, along
+    /// with its path.
+    pub fn scalars(
+        &self,
+    ) -> impl Iterator<Item = (String, &Spanned<SpannedValue>)> {
+        self.walk().filter(|(_, value)| {
+            !matches!(value.value().kind(), Kind::Array | Kind::Object)
+        })
+    }
+
+    /// Every string inside this one, along with its path.
+    pub fn strings(&self) -> impl Iterator<Item = (String, Spanned<&str>)> {
+        self.walk().filter_map(|(path, value)| {
+            let s = value.value().as_str()?;
+            Some((path, Spanned::from_span(s, Span::from(value.span()))))
+        })
+    }
+
+    /// Deep-merge `other` into this value, e.g. to apply a configuration
+    /// file's overrides.
+    ///
+    /// Objects are merged key by key, and arrays are combined according to
+    /// `strategy`. Any other value in `other` replaces the existing one.
+    /// Values keep the spans they were deserialized with, so they point into
+    /// whichever document they came from.
+    pub fn merge(&mut self, other: SpannedValue, strategy: MergeStrategy) {
+        match (self, other) {
+            (SpannedValue::Object(entries), SpannedValue::Object(other)) => {
+                for (key, value) in other {
+                    match entries.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, existing))
+                            if value
+                                .value()
+                                .is_mergeable(existing.value(), strategy) =>
+                        {
+                            existing
+                                .value_mut()
+                                .merge(value.into_value(), strategy);
+                        },
+                        Some(entry) => *entry = (key, value),
+                        None => entries.push((key, value)),
+                    }
+                }
+            },
+            (SpannedValue::Array(items), SpannedValue::Array(other))
+                if strategy == MergeStrategy::Append =>
+            {
+                items.extend(other);
+            },
+            (this, other) => *this = other,
+        }
+    }
+
+    /// Would [`SpannedValue::merge()`] combine this with `existing`, rather
+    /// than replacing it?
+    fn is_mergeable(
+        &self,
+        existing: &SpannedValue,
+        strategy: MergeStrategy,
+    ) -> bool {
+        match (existing, self) {
+            (SpannedValue::Object(_), SpannedValue::Object(_)) => true,
+            (SpannedValue::Array(_), SpannedValue::Array(_)) => {
+                strategy == MergeStrategy::Append
+            },
+            _ => false,
+        }
+    }
+
+    /// What sort of value this is.
+    pub fn kind(&self) -> Kind {
+        match self {
+            SpannedValue::Null => Kind::Null,
+            SpannedValue::Bool(_) => Kind::Bool,
+            SpannedValue::Number(_) => Kind::Number,
+            SpannedValue::String(_) => Kind::String,
+            SpannedValue::Array(_) => Kind::Array,
+            SpannedValue::Object(_) => Kind::Object,
+        }
+    }
+
+    /// A tree of every key and array element, e.g. for an editor's
+    /// document outline.
+    pub fn outline(&self) -> Vec<Symbol> {
+        match self {
+            SpannedValue::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| Symbol::new(i.to_string(), item.span(), item))
+                .collect(),
+            SpannedValue::Object(entries) => entries
+                .iter()
+                .map(|(key, value)| {
+                    Symbol::new(key.value().clone(), key.span(), value)
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Find the innermost value at `offset` in the document, e.g. to show
+    /// a tooltip when hovering over it.
+    ///
+    /// An offset inside a key finds that key's entry. This value's own span
+    /// isn't known, so `None` is returned if none of its children contain
+    /// the offset.
+    pub fn hover(&self, offset: usize) -> Option<Hover<'_>> {
+        self.hover_at(offset, &Path::ROOT)
+    }
+
+    fn hover_at(&self, offset: usize, path: &Path<'_>) -> Option<Hover<'_>> {
+        let contains =
+            |spanned: (usize, usize)| Span::from(spanned).contains(offset);
+
+        let (key, value, path) = match self {
+            SpannedValue::Array(items) => {
+                let (i, item) = items
+                    .iter()
+                    .enumerate()
+                    .find(|(_, item)| contains(item.span()))?;
+                (None, item, path.join(Segment::Index(i)))
+            },
+            SpannedValue::Object(entries) => {
+                let (key, value) = entries.iter().find(|(key, value)| {
+                    contains(key.span()) || contains(value.span())
+                })?;
+                (Some(key), value, path.join(Segment::Key(key.value())))
+            },
+            _ => return None,
+        };
+
+        if key.map_or(false, |key| contains(key.span())) {
+            return Some(Hover::new(&path, key, value));
+        }
+
+        value
+            .value()
+            .hover_at(offset, &path)
+            .or_else(|| Some(Hover::new(&path, key, value)))
+    }
+
+    /// Use the value as a [`Deserializer`] with an error type of your
+    /// choosing.
+    pub fn into_deserializer<E: Error>(self) -> SpannedValueDeserializer<E> {
+        SpannedValueDeserializer {
+            value: self,
+            span: None,
+            error: PhantomData,
+        }
+    }
+
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            SpannedValue::Null => Unexpected::Unit,
+            SpannedValue::Bool(b) => Unexpected::Bool(*b),
+            SpannedValue::Number(n) => match *n {
+                Number::Unsigned(n) => Unexpected::Unsigned(n),
+                Number::Signed(n) => Unexpected::Signed(n),
+                Number::Float(n) => Unexpected::Float(n),
+            },
+            SpannedValue::String(s) => Unexpected::Str(s),
+            SpannedValue::Array(_) => Unexpected::Seq,
+            SpannedValue::Object(_) => Unexpected::Map,
+        }
+    }
+}
+
+/// The iterator returned by [`SpannedValue::walk()`].
+#[derive(Debug, Clone)]
+pub struct Walk<'a> {
+    /// Values which haven't been visited yet, with the next one last.
+    stack: Vec<(String, &'a Spanned<SpannedValue>)>,
+}
+
+impl<'a> Walk<'a> {
+    fn push_children(&mut self, path: &str, value: &'a SpannedValue) {
+        let start = self.stack.len();
+
+        match value {
+            SpannedValue::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.stack.push((format!("{}[{}]", path, i), item));
+                }
+            },
+            SpannedValue::Object(entries) => {
+                for (key, value) in entries {
+                    let child = if path.is_empty() {
+                        key.value().clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    self.stack.push((child, value));
+                }
+            },
+            _ => {},
+        }
+
+        self.stack[start..].reverse();
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (String, &'a Spanned<SpannedValue>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, value) = self.stack.pop()?;
+        self.push_children(&path, value.value());
+        Some((path, value))
+    }
+}
+
+/// A single step when looking up a nested value.
+enum Step<'a> {
+    /// A JSON Pointer token, which is either a key or an index.
+    Token(Cow<'a, str>),
+    Key(&'a str),
+    Index(usize),
+}
+
+impl Step<'_> {
+    fn get<'v>(
+        &self,
+        value: &'v SpannedValue,
+    ) -> Option<&'v Spanned<SpannedValue>> {
+        match (self, value) {
+            (Step::Token(token), SpannedValue::Array(_)) => {
+                value.get_index(parse_index(token)?)
+            },
+            (Step::Token(key), _) => value.get(key),
+            (Step::Key(key), _) => value.get(key),
+            (Step::Index(index), _) => value.get_index(*index),
+        }
+    }
+
+    fn get_mut<'v>(
+        &self,
+        value: &'v mut SpannedValue,
+    ) -> Option<&'v mut Spanned<SpannedValue>> {
+        match (self, &*value) {
+            (Step::Token(token), SpannedValue::Array(_)) => {
+                value.get_index_mut(parse_index(token)?)
+            },
+            (Step::Token(key), _) => value.get_mut(key),
+            (Step::Key(key), _) => value.get_mut(key),
+            (Step::Index(index), _) => value.get_index_mut(*index),
+        }
+    }
+}
+
+/// Parse an array index, which has to be written without a sign or
+/// leading zeroes.
+fn parse_index(token: &str) -> Option<usize> {
+    let canonical = token == "0"
+        || !token.is_empty()
+            && !token.starts_with('0')
+            && token.bytes().all(|b| b.is_ascii_digit());
+
+    if canonical {
+        token.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Split a path like `a.b[0][1].c` into its steps.
+fn parse_path(path: &str) -> Option<Vec<Step<'_>>> {
+    let mut steps = Vec::new();
+
+    for (i, part) in path.split('.').enumerate() {
+        let (key, mut indices) = match part.find('[') {
+            Some(bracket) => part.split_at(bracket),
+            None => (part, ""),
+        };
+
+        // only the first part may start with an index (e.g. `[0].a`)
+        if !key.is_empty() {
+            steps.push(Step::Key(key));
+        } else if i > 0 || indices.is_empty() {
+            return None;
+        }
+
+        while !indices.is_empty() {
+            let close = indices.find(']')?;
+            steps.push(Step::Index(parse_index(&indices[1..close])?));
+            indices = &indices[close + 1..];
+
+            if !indices.is_empty() && !indices.starts_with('[') {
+                return None;
+            }
+        }
+    }
+
+    Some(steps)
+}
+
+/// Split a JSON Pointer into its unescaped tokens.
+fn parse_pointer(pointer: &str) -> Option<Vec<Step<'_>>> {
+    if !pointer.starts_with('/') {
+        return None;
+    }
+
+    Some(
+        pointer[1..]
+            .split('/')
+            .map(|token| {
+                Step::Token(if token.contains('~') {
+                    Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+                } else {
+                    Cow::Borrowed(token)
+                })
+            })
+            .collect(),
+    )
+}
+
+impl Default for SpannedValue {
+    fn default() -> Self { SpannedValue::Null }
+}
+
+impl From<bool> for SpannedValue {
+    fn from(b: bool) -> Self { SpannedValue::Bool(b) }
+}
+
+impl From<u64> for SpannedValue {
+    fn from(n: u64) -> Self { SpannedValue::Number(Number::Unsigned(n)) }
+}
+
+impl From<i64> for SpannedValue {
+    fn from(n: i64) -> Self { SpannedValue::Number(Number::from(n)) }
+}
+
+impl From<f64> for SpannedValue {
+    fn from(n: f64) -> Self { SpannedValue::Number(Number::Float(n)) }
+}
+
+impl From<String> for SpannedValue {
+    fn from(s: String) -> Self { SpannedValue::String(s) }
+}
+
+impl From<&str> for SpannedValue {
+    fn from(s: &str) -> Self { SpannedValue::String(s.into()) }
+}
+
+/// How [`SpannedValue::merge()`] combines two arrays.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The new array replaces the existing one.
+    Replace,
+    /// The new array's elements are added to the end of the existing one.
+    Append,
+}
+
+/// The type of a [`SpannedValue`], without its contents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+/// An entry in the outline returned by [`SpannedValue::outline()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    /// The entry's key, or its index in an array.
+    pub name: String,
+    /// The span of the key, or of the whole element in an array.
+    pub name_span: Span,
+    /// The span of the value.
+    pub span: Span,
+    pub kind: Kind,
+    /// The keys and elements inside the value.
+    pub children: Vec<Symbol>,
+}
+
+impl Symbol {
+    fn new(
+        name: String,
+        name_span: (usize, usize),
+        value: &Spanned<SpannedValue>,
+    ) -> Self {
+        Symbol {
+            name,
+            name_span: Span::from(name_span),
+            span: Span::from(value.span()),
+            kind: value.value().kind(),
+            children: value.value().outline(),
+        }
+    }
+
+    /// The span covering both the name and the value (e.g. the `range` of
+    /// an LSP `DocumentSymbol`).
+    pub fn full_span(&self) -> Span { self.name_span.union(self.span) }
+}
+
+/// The value found by [`SpannedValue::hover()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hover<'a> {
+    /// The path to the value, formatted like a [`Path`].
+    pub path: String,
+    /// The value's key, if it is an entry in an object.
+    pub key: Option<&'a Spanned<String>>,
+    pub value: &'a Spanned<SpannedValue>,
+}
+
+impl<'a> Hover<'a> {
+    fn new(
+        path: &Path<'_>,
+        key: Option<&'a Spanned<String>>,
+        value: &'a Spanned<SpannedValue>,
+    ) -> Self {
+        Hover {
+            path: path.to_string(),
+            key,
+            value,
+        }
+    }
+}
+
+/// A number inside a [`SpannedValue`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Number {
+    /// An integer which is zero or greater.
+    Unsigned(u64),
+    /// An integer which is less than zero.
+    Signed(i64),
+    Float(f64),
+}
+
+impl Number {
+    pub fn as_u64(self) -> Option<u64> {
+        match self {
+            Number::Unsigned(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(self) -> Option<i64> {
+        match self {
+            Number::Unsigned(n) if n <= i64::MAX as u64 => Some(n as i64),
+            Number::Signed(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The number as a float, which may lose precision for large integers.
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Number::Unsigned(n) => n as f64,
+            Number::Signed(n) => n as f64,
+            Number::Float(n) => n,
+        }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        if n < 0 {
+            Number::Signed(n)
+        } else {
+            Number::Unsigned(n as u64)
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Unsigned(n) => Display::fmt(n, f),
+            Number::Signed(n) => Display::fmt(n, f),
+            Number::Float(n) => Display::fmt(n, f),
+        }
+    }
+}
+
+/// A dynamically typed value without any spans, produced by
+/// [`Unspanned::into_plain()`] on a [`SpannedValue`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Value>),
+    /// The entries of a map, in document order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Default for Value {
+    fn default() -> Self { Value::Null }
+}
+
+impl Unspanned for SpannedValue {
+    type Plain = Value;
+
+    fn into_plain(self) -> Value {
+        match self {
+            SpannedValue::Null => Value::Null,
+            SpannedValue::Bool(b) => Value::Bool(b),
+            SpannedValue::Number(n) => Value::Number(n),
+            SpannedValue::String(s) => Value::String(s),
+            SpannedValue::Array(items) => Value::Array(items.into_plain()),
+            SpannedValue::Object(entries) => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.into_value(), value.into_plain()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => ser.serialize_unit(),
+            Value::Bool(b) => ser.serialize_bool(*b),
+            Value::Number(n) => n.serialize(ser),
+            Value::String(s) => ser.serialize_str(s),
+            Value::Array(items) => ser.collect_seq(items),
+            Value::Object(entries) => {
+                ser.collect_map(entries.iter().map(|(k, v)| (k, v)))
+            },
+        }
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Number::Unsigned(n) => ser.serialize_u64(n),
+            Number::Signed(n) => ser.serialize_i64(n),
+            Number::Float(n) => ser.serialize_f64(n),
+        }
+    }
+}
+
+impl Serialize for SpannedValue {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SpannedValue::Null => ser.serialize_unit(),
+            SpannedValue::Bool(b) => ser.serialize_bool(*b),
+            SpannedValue::Number(n) => n.serialize(ser),
+            SpannedValue::String(s) => ser.serialize_str(s),
+            SpannedValue::Array(items) => ser.collect_seq(items),
+            SpannedValue::Object(entries) => {
+                ser.collect_map(entries.iter().map(|(k, v)| (k, v)))
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SpannedValue {
+    /// Deserialize a value with the default [`Limits`].
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        SpannedValue::deserialize_with_limits(de, &Limits::default())
+    }
+}
+
+impl SpannedValue {
+    /// Deserialize a value, failing as soon as it goes over `limits`.
+    ///
+    /// The error includes the span of the value which went over the limit,
+    /// when the format reports one.
+    ///
+    /// ```rust
+    /// use serde::de::{value::Error, IntoDeserializer};
+    /// use serde_spanned::{value::Limits, Spanned, SpannedValue};
+    ///
+    /// // what a span-aware format would produce for `{"n": "...."}`
+    /// let doc = SpannedValue::Object(vec![(
+    ///     Spanned::new(String::from("n"), 1, 4),
+    ///     Spanned::new(SpannedValue::from("...."), 6, 12),
+    /// )]);
+    /// let limits = Limits {
+    ///     max_string_len: 3,
+    ///     ..Limits::default()
+    /// };
+    ///
+    /// let err = SpannedValue::deserialize_with_limits(
+    ///     doc.into_deserializer::<Error>(),
+    ///     &limits,
+    /// )
+    /// .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "a string is longer than 3 bytes (at 6..12)"
+    /// );
+    /// ```
+    pub fn deserialize_with_limits<'de, D: Deserializer<'de>>(
+        de: D,
+        limits: &Limits,
+    ) -> Result<Self, D::Error> {
+        let state = State {
+            limits,
+            nodes: Cell::new(0),
+            exceeded: Cell::new(None),
+        };
+        let value = ValueSeed {
+            state: &state,
+            depth: 0,
+        }
+        .deserialize(de)?;
+
+        // Only the root isn't wrapped in a Spanned, so there's no span to
+        // report
+        match state.exceeded.get() {
+            Some(exceeded) => Err(Error::custom(exceeded.message(limits))),
+            None => Ok(value),
+        }
+    }
+}
+
+/// Limits on the size of a [`SpannedValue`] being deserialized, for
+/// documents which come from untrusted input.
+///
+/// The default only limits nesting to 128 levels, the same as `serde_json`,
+/// so deeply nested input can't overflow the stack.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// How many arrays and objects a value may be nested inside.
+    pub max_depth: usize,
+    /// The number of values in the whole document, including arrays and
+    /// objects but not keys.
+    pub max_nodes: usize,
+    /// The length of the longest string or key, in bytes.
+    pub max_string_len: usize,
+}
+
+impl Limits {
+    pub const UNLIMITED: Limits = Limits {
+        max_depth: usize::MAX,
+        max_nodes: usize::MAX,
+        max_string_len: usize::MAX,
+    };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 128,
+            ..Limits::UNLIMITED
+        }
+    }
+}
+
+/// The limit a value went over.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Exceeded {
+    Depth,
+    Nodes,
+    StringLen,
+}
+
+impl Exceeded {
+    fn message(self, limits: &Limits) -> String {
+        match self {
+            Exceeded::Depth => format!(
+                "a value is nested more than {} levels deep",
+                limits.max_depth
+            ),
+            Exceeded::Nodes => {
+                format!(
+                    "the document has more than {} values",
+                    limits.max_nodes
+                )
+            },
+            Exceeded::StringLen => format!(
+                "a string is longer than {} bytes",
+                limits.max_string_len
+            ),
+        }
+    }
+}
+
+/// Shared by every value in the document being deserialized.
+struct State<'a> {
+    limits: &'a Limits,
+    nodes: Cell<usize>,
+    /// The limit a value went over, which is reported with the span of the
+    /// nearest `Spanned` around it.
+    exceeded: Cell<Option<Exceeded>>,
+}
+
+/// Deserializes a single value, checking the depth and node count first.
+#[derive(Copy, Clone)]
+struct ValueSeed<'a> {
+    state: &'a State<'a>,
+    depth: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_> {
+    type Value = SpannedValue;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<SpannedValue, D::Error> {
+        let nodes = self.state.nodes.get() + 1;
+        self.state.nodes.set(nodes);
+
+        let exceeded = if self.depth > self.state.limits.max_depth {
+            Some(Exceeded::Depth)
+        } else if nodes > self.state.limits.max_nodes {
+            Some(Exceeded::Nodes)
+        } else {
+            None
+        };
+
+        match exceeded {
+            Some(exceeded) => {
+                // Skip the value, so the error is reported once its span is
+                // known
+                self.state.exceeded.set(Some(exceeded));
+                de.deserialize_ignored_any(IgnoredAny)?;
+                Ok(SpannedValue::Null)
+            },
+            None => de.deserialize_any(ValueVisitor(self)),
+        }
+    }
+}
+
+/// Deserializes a value inside a [`Spanned`], reporting any limit it went
+/// over with its span.
+struct SpannedValueSeed<'a>(ValueSeed<'a>);
+
+impl<'de> DeserializeSeed<'de> for SpannedValueSeed<'_> {
+    type Value = Spanned<SpannedValue>;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<Spanned<SpannedValue>, D::Error> {
+        let state = self.0.state;
+        let value = SpannedSeed(self.0).deserialize(de)?;
+
+        match state.exceeded.take() {
+            Some(exceeded) => Err(Error::custom(format_args!(
+                "{} (at {}..{})",
+                exceeded.message(state.limits),
+                value.start(),
+                value.end()
+            ))),
+            None => Ok(value),
+        }
+    }
+}
+
+struct ValueVisitor<'a>(ValueSeed<'a>);
+
+impl ValueVisitor<'_> {
+    /// Is `s` short enough? If not, the string is replaced by a `Null` and
+    /// reported once its span is known.
+    fn fits(&self, s: &str) -> bool {
+        let fits = s.len() <= self.0.state.limits.max_string_len;
+        if !fits {
+            self.0.state.exceeded.set(Some(Exceeded::StringLen));
+        }
+        fits
+    }
+
+    /// The seed for the values inside an array or object.
+    fn child(&self) -> SpannedValueSeed<'_> {
+        SpannedValueSeed(ValueSeed {
+            state: self.0.state,
+            depth: self.0.depth + 1,
+        })
+    }
+}
+
+impl<'de> Visitor<'de> for ValueVisitor<'_> {
+    type Value = SpannedValue;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: Error>(self, b: bool) -> Result<Self::Value, E> {
+        Ok(SpannedValue::from(b))
+    }
+
+    fn visit_i64<E: Error>(self, n: i64) -> Result<Self::Value, E> {
+        Ok(SpannedValue::from(n))
+    }
+
+    fn visit_u64<E: Error>(self, n: u64) -> Result<Self::Value, E> {
+        Ok(SpannedValue::from(n))
+    }
+
+    fn visit_f64<E: Error>(self, n: f64) -> Result<Self::Value, E> {
+        Ok(SpannedValue::from(n))
+    }
+
+    fn visit_str<E: Error>(self, s: &str) -> Result<Self::Value, E> {
+        if self.fits(s) {
+            Ok(SpannedValue::from(s))
+        } else {
+            Ok(SpannedValue::Null)
+        }
+    }
+
+    fn visit_string<E: Error>(self, s: String) -> Result<Self::Value, E> {
+        if self.fits(&s) {
+            Ok(SpannedValue::from(s))
+        } else {
+            Ok(SpannedValue::Null)
+        }
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(SpannedValue::Null)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(SpannedValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<Self::Value, D::Error> {
+        de.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        de: D,
+    ) -> Result<Self::Value, D::Error> {
+        de.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Self::Value, A::Error> {
+        let mut items = Vec::new();
+
+        while let Some(item) = seq.next_element_seed(self.child())? {
+            items.push(item);
+        }
+
+        Ok(SpannedValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Self::Value, A::Error> {
+        let limits = self.0.state.limits;
+        let mut entries: Vec<(Spanned<String>, Spanned<SpannedValue>)> =
+            Vec::new();
+        // Scanning the entries for every key would be quadratic in the size
+        // of the object
+        let mut seen = BTreeSet::new();
+
+        while let Some(key) = map.next_key::<Spanned<String>>()? {
+            let (start, end) = key.span();
+
+            if key.value().len() > limits.max_string_len {
+                return Err(Error::custom(format_args!(
+                    "{} (at {}..{})",
+                    Exceeded::StringLen.message(limits),
+                    start,
+                    end
+                )));
+            }
+            if !seen.insert(key.value().clone()) {
+                return Err(Error::custom(format_args!(
+                    "duplicate key `{}` (at {}..{})",
+                    key, start, end
+                )));
+            }
+
+            let value = map.next_value_seed(self.child())?;
+            entries.push((key, value));
+        }
+
+        Ok(SpannedValue::Object(entries))
+    }
+}
+
+/// A [`Deserializer`] which reads from a [`SpannedValue`], created with
+/// [`IntoDeserializer`].
+///
+/// When a [`Spanned`] value is deserialized, it gets the span recorded for
+/// the node. The root of a plain `SpannedValue` has no span, so asking for
+/// a `Spanned` value there is an error.
+#[derive(Debug, Clone)]
+pub struct SpannedValueDeserializer<E> {
+    value: SpannedValue,
+    span: Option<Span>,
+    error: PhantomData<E>,
+}
+
+impl<'de, E: Error> IntoDeserializer<'de, E> for SpannedValue {
+    type Deserializer = SpannedValueDeserializer<E>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        SpannedValue::into_deserializer(self)
+    }
+}
+
+impl<'de, E: Error> IntoDeserializer<'de, E> for Spanned<SpannedValue> {
+    type Deserializer = SpannedValueDeserializer<E>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        SpannedValueDeserializer {
+            span: Some(Span::from(self.span())),
+            value: self.into_value(),
+            error: PhantomData,
+        }
+    }
+}
+
+impl<'de, E: Error> Deserializer<'de> for SpannedValueDeserializer<E> {
+    type Error = E;
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf unit unit_struct seq tuple tuple_struct map identifier
+        ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        match self.value {
+            SpannedValue::Null => visitor.visit_unit(),
+            SpannedValue::Bool(b) => visitor.visit_bool(b),
+            SpannedValue::Number(n) => match n {
+                Number::Unsigned(n) => visitor.visit_u64(n),
+                Number::Signed(n) => visitor.visit_i64(n),
+                Number::Float(n) => visitor.visit_f64(n),
+            },
+            SpannedValue::String(s) => visitor.visit_string(s),
+            SpannedValue::Array(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            },
+            SpannedValue::Object(entries) => {
+                let entries = entries.into_iter().map(|(key, value)| {
+                    let span = Span::from(key.span());
+                    let key = SpannedValue::String(key.into_value());
+                    (Spanned::from_span(key, span), value)
+                });
+                let mut map = MapDeserializer::new(entries);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            },
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        match self.value {
+            SpannedValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        if !crate::de::is_spanned(name, fields) {
+            return self.deserialize_any(visitor);
+        }
+
+        match self.span {
+            Some(span) => visitor.visit_map(SpannedDeserializer::new(
+                span.start(),
+                span.end(),
+                self,
+            )),
+            None => Err(Error::custom("the root value doesn't have a span")),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        match self.value {
+            SpannedValue::String(variant) => {
+                visitor.visit_enum(variant.into_deserializer())
+            },
+            SpannedValue::Object(entries) if entries.len() == 1 => {
+                let (variant, value) = entries.into_iter().next().unwrap();
+
+                visitor.visit_enum(EnumDeserializer {
+                    variant: variant.into_value(),
+                    value,
+                    error: PhantomData,
+                })
+            },
+            other => Err(Error::invalid_type(
+                other.unexpected(),
+                &"a string or a map with a single key",
+            )),
+        }
+    }
+}
+
+/// An externally tagged enum variant, written as a map with a single key.
+struct EnumDeserializer<E> {
+    variant: String,
+    value: Spanned<SpannedValue>,
+    error: PhantomData<E>,
+}
+
+impl<'de, E: Error> EnumAccess<'de> for EnumDeserializer<E> {
+    type Error = E;
+    type Variant = SpannedValueDeserializer<E>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), E> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.value.into_deserializer()))
+    }
+}
+
+impl<'de, E: Error> VariantAccess<'de> for SpannedValueDeserializer<E> {
+    type Error = E;
+
+    fn unit_variant(self) -> Result<(), E> {
+        match self.value {
+            SpannedValue::Null => Ok(()),
+            other => {
+                Err(Error::invalid_type(other.unexpected(), &"a unit variant"))
+            },
+        }
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, E> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        self.deserialize_map(visitor)
+    }
+}
@@ -0,0 +1,650 @@
+//! Visiting every span inside a value.
+
+use crate::{Secret, Spanned, SpannedFromStr, TotalOrd};
+use core::fmt::{self, Display, Formatter};
+
+/// Something which may contain [`Spanned`] values.
+///
+/// This can be derived with `#[derive(WalkSpans)]` when the `derive` feature
+/// is enabled. Fields which don't implement `WalkSpans` can be skipped with
+/// `#[walk_spans(skip)]`.
+///
+/// # Map Keys
+///
+/// Maps only walk their values, each under a [`Segment::Key`] named after
+/// its key. The keys themselves aren't walked, so the span of a
+/// `Spanned` key (e.g. the keys of a `SpannedValue`'s objects) is never
+/// visited, and a `SpanMap` can't give one back.
+pub trait WalkSpans {
+    /// Call `visitor` with the path and span of every [`Spanned`] value
+    /// inside `self`, parents before children.
+    fn walk_spans(
+        &self,
+        path: &Path<'_>,
+        visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+    );
+
+    /// Should the text covered by this value's spans be hidden, like the
+    /// text of a [`Secret`]?
+    ///
+    /// Wrappers like [`Spanned`] and `Option` check this so the spans
+    /// around a secret are also visited with a [redacted](Path::is_redacted)
+    /// path.
+    fn is_secret(&self) -> bool { false }
+}
+
+/// Find the spans in `src` which editors should let the user fold, i.e.
+/// those covering at least `min_lines` lines.
+///
+/// `value` should have been deserialized from `src`. No format is needed,
+/// because the spans already say where each value is.
+///
+/// [`WalkSpans`] doesn't say what kind of value each span belongs to, so
+/// this includes every [`Spanned`] value, not just tables and arrays. That
+/// means multi-line strings are included too, which editors usually fold
+/// as well. Spans which end before they start are skipped.
+///
+/// The spans are sorted by where they start, with outer spans before the
+/// spans nested inside them, and duplicates removed.
+///
+/// ```rust
+/// use serde_spanned::{walk::folding_ranges, Span, Spanned};
+///
+/// let src = "[\n  [\n    1\n  ],\n  [2]\n]";
+/// let inner = vec![Spanned::new(1, 10, 11), Spanned::new(2, 20, 21)];
+/// let outer = vec![
+///     Spanned::new(vec![inner[0]], 4, 15),
+///     Spanned::new(vec![inner[1]], 19, 22),
+/// ];
+/// let value = Spanned::new(outer, 0, src.len());
+///
+/// assert_eq!(
+///     folding_ranges(&value, src, 2),
+///     [Span::new(0, src.len()), Span::new(4, 15)]
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn folding_ranges<W: WalkSpans + ?Sized>(
+    value: &W,
+    src: &str,
+    min_lines: usize,
+) -> alloc::vec::Vec<crate::Span> {
+    let index = crate::LineIndex::new(src);
+    let mut ranges = alloc::vec::Vec::new();
+
+    value.walk_spans(&Path::ROOT, &mut |_, (start, end)| {
+        if end < start {
+            return;
+        }
+
+        // A span ending with a newline shouldn't include the next line
+        let last = if end > start { end - 1 } else { end };
+        let lines = index.position(last).line - index.position(start).line + 1;
+
+        if lines >= min_lines {
+            ranges.push(crate::Span::new(start, end));
+        }
+    });
+
+    ranges.sort_by_key(|span| (span.start(), core::cmp::Reverse(span.end())));
+    ranges.dedup();
+    ranges
+}
+
+/// Check that the span of every [`Spanned`] value inside `value` is contained
+/// in the span of the nearest `Spanned` value around it.
+///
+/// The first violation found is returned, in the order the spans are
+/// walked.
+///
+/// ```rust
+/// use serde_spanned::{walk::check_nesting, Spanned};
+///
+/// let ok = Spanned::new(vec![Spanned::new(1, 1, 2)], 0, 5);
+/// assert!(check_nesting(&ok).is_ok());
+///
+/// let items = vec![Spanned::new(1, 1, 2), Spanned::new(2, 4, 9)];
+/// let bad = Spanned::new(items, 0, 5);
+/// let err = check_nesting(&bad).unwrap_err();
+/// assert_eq!(err.to_string(), "[1] (4..9) isn't inside <root> (0..5)");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn check_nesting<W: WalkSpans + ?Sized>(
+    value: &W,
+) -> Result<(), NestingError> {
+    use alloc::{string::ToString, vec::Vec};
+
+    // The enclosing spans, outermost first
+    let mut ancestors: Vec<(Vec<OwnedSegment>, crate::Span)> = Vec::new();
+    let mut error = None;
+
+    value.walk_spans(&Path::ROOT, &mut |path, span| {
+        if error.is_some() {
+            return;
+        }
+
+        let segments = path.owned_segments();
+        let span = crate::Span::from(span);
+
+        while let Some((parent, _)) = ancestors.last() {
+            if segments.starts_with(parent) {
+                break;
+            }
+            ancestors.pop();
+        }
+
+        if let Some((parent, parent_span)) = ancestors.last() {
+            if !parent_span.contains_span(span) {
+                error = Some(NestingError {
+                    parent: display_segments(parent),
+                    parent_span: *parent_span,
+                    child: path.to_string(),
+                    child_span: span,
+                });
+            }
+        }
+
+        ancestors.push((segments, span));
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Display owned segments the same way as the [`Path`] they came from.
+#[cfg(feature = "alloc")]
+fn display_segments(segments: &[OwnedSegment]) -> alloc::string::String {
+    use core::fmt::Write;
+
+    let mut path = alloc::string::String::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        let _ = match segment {
+            OwnedSegment::Index(index) => write!(path, "[{}]", index),
+            OwnedSegment::Key(key) if i == 0 => write!(path, "{}", key),
+            OwnedSegment::Key(key) => write!(path, ".{}", key),
+        };
+    }
+
+    path
+}
+
+/// A span which isn't inside the span of the value around it, as found by
+/// [`check_nesting()`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestingError {
+    pub parent: alloc::string::String,
+    pub parent_span: crate::Span,
+    pub child: alloc::string::String,
+    pub child_span: crate::Span,
+}
+
+#[cfg(feature = "alloc")]
+impl Display for NestingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fn name(path: &str) -> &str {
+            if path.is_empty() {
+                "<root>"
+            } else {
+                path
+            }
+        }
+
+        write!(
+            f,
+            "{} ({}..{}) isn't inside {} ({}..{})",
+            name(&self.child),
+            self.child_span.start(),
+            self.child_span.end(),
+            name(&self.parent),
+            self.parent_span.start(),
+            self.parent_span.end()
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NestingError {}
+
+/// The location of a value, relative to the root of the value being walked.
+///
+/// Paths are a linked list of [`Segment`]s living on the stack, so walking
+/// doesn't need to allocate. They are displayed in a dotted form like
+/// `servers[0].tls.cert`.
+#[derive(Debug, Copy, Clone)]
+pub struct Path<'a> {
+    parent: Option<&'a Path<'a>>,
+    segment: Option<Segment<'a>>,
+    redacted: bool,
+}
+
+impl Path<'static> {
+    pub const ROOT: Path<'static> = Path {
+        parent: None,
+        segment: None,
+        redacted: false,
+    };
+}
+
+impl<'a> Path<'a> {
+    pub fn join<'b>(&'b self, segment: Segment<'b>) -> Path<'b> {
+        Path {
+            parent: Some(self),
+            segment: Some(segment),
+            redacted: self.redacted,
+        }
+    }
+
+    /// The same path, marked as being inside a secret value. Every path
+    /// joined onto it is redacted too.
+    pub fn redacted(&self) -> Path<'a> {
+        Path {
+            redacted: true,
+            ..*self
+        }
+    }
+
+    /// Is this path inside a secret value, whose text shouldn't be shown?
+    pub fn is_redacted(&self) -> bool { self.redacted }
+
+    pub fn is_root(&self) -> bool { self.segment.is_none() }
+
+    /// The last segment in this path, or `None` for the root.
+    pub fn segment(&self) -> Option<Segment<'a>> { self.segment }
+
+    pub fn parent(&self) -> Option<&'a Path<'a>> { self.parent }
+
+    /// The number of segments in this path.
+    pub fn depth(&self) -> usize {
+        match self.parent {
+            Some(parent) => parent.depth() + 1,
+            None => 0,
+        }
+    }
+
+    /// Copy this path's segments, root first, so paths can be stored and
+    /// compared segment by segment. Comparing the displayed form instead
+    /// would mix up keys containing `.` or `[` with nested paths.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn owned_segments(&self) -> alloc::vec::Vec<OwnedSegment> {
+        use alloc::string::ToString;
+
+        let mut segments = match self.parent {
+            Some(parent) => parent.owned_segments(),
+            None => alloc::vec::Vec::new(),
+        };
+
+        match self.segment {
+            Some(Segment::Index(index)) => {
+                segments.push(OwnedSegment::Index(index))
+            },
+            Some(Segment::Field(name)) => {
+                segments.push(OwnedSegment::Key(name.to_string()))
+            },
+            Some(Segment::Key(key)) => {
+                segments.push(OwnedSegment::Key(key.to_string()))
+            },
+            None => {},
+        }
+
+        segments
+    }
+}
+
+impl Default for Path<'static> {
+    fn default() -> Self { Path::ROOT }
+}
+
+impl Display for Path<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (parent, segment) = match (self.parent, self.segment) {
+            (Some(parent), Some(segment)) => (parent, segment),
+            _ => return Ok(()),
+        };
+
+        Display::fmt(parent, f)?;
+
+        match segment {
+            Segment::Index(index) => write!(f, "[{}]", index),
+            Segment::Field(name) if parent.is_root() => f.write_str(name),
+            Segment::Field(name) => write!(f, ".{}", name),
+            Segment::Key(key) if parent.is_root() => write!(f, "{}", key),
+            Segment::Key(key) => write!(f, ".{}", key),
+        }
+    }
+}
+
+/// A single step in a [`Path`].
+#[derive(Copy, Clone)]
+pub enum Segment<'a> {
+    /// A named field on a struct.
+    Field(&'a str),
+    /// An element in a sequence.
+    Index(usize),
+    /// An entry in a map.
+    Key(&'a dyn Display),
+}
+
+/// An owned copy of a [`Segment`].
+///
+/// Struct fields and map keys both become a key in the serialized document,
+/// so they aren't told apart.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum OwnedSegment {
+    Key(alloc::string::String),
+    Index(usize),
+}
+
+impl fmt::Debug for Segment<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Field(name) => f.debug_tuple("Field").field(name).finish(),
+            Segment::Index(index) => {
+                f.debug_tuple("Index").field(index).finish()
+            },
+            Segment::Key(key) => f
+                .debug_tuple("Key")
+                .field(&format_args!("{}", key))
+                .finish(),
+        }
+    }
+}
+
+impl<T: WalkSpans> WalkSpans for Spanned<T> {
+    fn walk_spans(
+        &self,
+        path: &Path<'_>,
+        visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+    ) {
+        if self.value().is_secret() {
+            let path = path.redacted();
+            visitor(&path, self.span());
+            self.value().walk_spans(&path, visitor);
+        } else {
+            visitor(path, self.span());
+            self.value().walk_spans(path, visitor);
+        }
+    }
+
+    fn is_secret(&self) -> bool { self.value().is_secret() }
+}
+
+impl<T: WalkSpans + ?Sized> WalkSpans for &T {
+    fn walk_spans(
+        &self,
+        path: &Path<'_>,
+        visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+    ) {
+        (**self).walk_spans(path, visitor);
+    }
+
+    fn is_secret(&self) -> bool { (**self).is_secret() }
+}
+
+impl<T: WalkSpans> WalkSpans for Option<T> {
+    fn walk_spans(
+        &self,
+        path: &Path<'_>,
+        visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+    ) {
+        if let Some(value) = self {
+            value.walk_spans(path, visitor);
+        }
+    }
+
+    fn is_secret(&self) -> bool {
+        self.as_ref().map_or(false, WalkSpans::is_secret)
+    }
+}
+
+impl<T: WalkSpans> WalkSpans for [T] {
+    fn walk_spans(
+        &self,
+        path: &Path<'_>,
+        visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+    ) {
+        for (i, item) in self.iter().enumerate() {
+            item.walk_spans(&path.join(Segment::Index(i)), visitor);
+        }
+    }
+}
+
+macro_rules! arrays {
+    ($($len:literal)*) => {
+        $(
+            impl<T: WalkSpans> WalkSpans for [T; $len] {
+                fn walk_spans(
+                    &self,
+                    path: &Path<'_>,
+                    visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+                ) {
+                    self[..].walk_spans(path, visitor);
+                }
+            }
+        )*
+    };
+}
+
+arrays! {
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
+impl<T: WalkSpans> WalkSpans for Secret<T> {
+    fn walk_spans(
+        &self,
+        path: &Path<'_>,
+        visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+    ) {
+        self.expose().walk_spans(&path.redacted(), visitor);
+    }
+
+    fn is_secret(&self) -> bool { true }
+}
+
+impl<T> WalkSpans for SpannedFromStr<T> {
+    fn walk_spans(
+        &self,
+        path: &Path<'_>,
+        visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+    ) {
+        visitor(path, self.0.span());
+    }
+}
+
+macro_rules! leaves {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl WalkSpans for $ty {
+                fn walk_spans(
+                    &self,
+                    _path: &Path<'_>,
+                    _visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+                ) {
+                }
+            }
+        )*
+    };
+}
+
+leaves!(
+    (),
+    bool,
+    char,
+    str,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    TotalOrd<f32>,
+    TotalOrd<f64>,
+);
+
+macro_rules! tuples {
+    ($( ($($index:tt $name:ident),+) )*) => {
+        $(
+            impl<$($name: WalkSpans),+> WalkSpans for ($($name,)+) {
+                fn walk_spans(
+                    &self,
+                    path: &Path<'_>,
+                    visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+                ) {
+                    $(
+                        self.$index
+                            .walk_spans(&path.join(Segment::Index($index)), visitor);
+                    )+
+                }
+            }
+        )*
+    };
+}
+
+tuples! {
+    (0 A)
+    (0 A, 1 B)
+    (0 A, 1 B, 2 C)
+    (0 A, 1 B, 2 C, 3 D)
+    (0 A, 1 B, 2 C, 3 D, 4 E)
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F)
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G)
+    (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H)
+}
+
+#[cfg(feature = "alloc")]
+mod alloc_impls {
+    use super::{Path, Segment, WalkSpans};
+    use alloc::{
+        borrow::Cow,
+        boxed::Box,
+        collections::{BTreeMap, VecDeque},
+        string::String,
+        vec::Vec,
+    };
+    use core::fmt::Display;
+
+    leaves!(String, Cow<'_, str>);
+
+    impl<T> WalkSpans for crate::SpannedOther<T> {
+        fn walk_spans(
+            &self,
+            path: &Path<'_>,
+            visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+        ) {
+            visitor(path, self.span());
+        }
+    }
+
+    impl WalkSpans for crate::SpannedValue {
+        fn walk_spans(
+            &self,
+            path: &Path<'_>,
+            visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+        ) {
+            match self {
+                crate::SpannedValue::Array(items) => {
+                    items.walk_spans(path, visitor)
+                },
+                crate::SpannedValue::Object(entries) => {
+                    for (key, value) in entries {
+                        let path = path.join(Segment::Key(key.value()));
+                        value.walk_spans(&path, visitor);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    impl<T: WalkSpans + ?Sized> WalkSpans for Box<T> {
+        fn walk_spans(
+            &self,
+            path: &Path<'_>,
+            visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+        ) {
+            (**self).walk_spans(path, visitor);
+        }
+
+        fn is_secret(&self) -> bool { (**self).is_secret() }
+    }
+
+    impl<T: WalkSpans> WalkSpans for Vec<T> {
+        fn walk_spans(
+            &self,
+            path: &Path<'_>,
+            visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+        ) {
+            self.as_slice().walk_spans(path, visitor);
+        }
+    }
+
+    impl<T: WalkSpans> WalkSpans for VecDeque<T> {
+        fn walk_spans(
+            &self,
+            path: &Path<'_>,
+            visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+        ) {
+            for (i, item) in self.iter().enumerate() {
+                item.walk_spans(&path.join(Segment::Index(i)), visitor);
+            }
+        }
+    }
+
+    impl<K, V> WalkSpans for BTreeMap<K, V>
+    where
+        K: Display,
+        V: WalkSpans,
+    {
+        fn walk_spans(
+            &self,
+            path: &Path<'_>,
+            visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+        ) {
+            for (key, value) in self {
+                value.walk_spans(&path.join(Segment::Key(key)), visitor);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod std_impls {
+    use super::{Path, Segment, WalkSpans};
+    use core::fmt::Display;
+    use std::{
+        collections::HashMap,
+        ffi::{OsStr, OsString},
+        path::{Path as FsPath, PathBuf},
+    };
+
+    leaves!(OsStr, OsString, FsPath, PathBuf);
+
+    impl<K, V, S> WalkSpans for HashMap<K, V, S>
+    where
+        K: Display,
+        V: WalkSpans,
+    {
+        fn walk_spans(
+            &self,
+            path: &Path<'_>,
+            visitor: &mut dyn FnMut(&Path<'_>, (usize, usize)),
+        ) {
+            for (key, value) in self {
+                value.walk_spans(&path.join(Segment::Key(key)), visitor);
+            }
+        }
+    }
+}
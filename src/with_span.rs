@@ -0,0 +1,165 @@
+use crate::{Spanned, Visitor};
+use core::marker::PhantomData;
+use serde::{
+    ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub(crate) const NAME: &str = "Spanned";
+pub(crate) const VALUE: &str = "value";
+pub(crate) const START: &str = "start";
+pub(crate) const END: &str = "end";
+
+pub(crate) const FIELDS: &[&str] = &[VALUE, START, END];
+
+/// A wrapper which serializes a [`Spanned`] value along with its span.
+///
+/// By default `Spanned<T>` serializes transparently as `T`. Wrapping it in
+/// `WithSpan` will instead serialize a struct with `value`, `start`, and `end`
+/// fields, which is handy for debugging dumps or for APIs that need to
+/// expose source locations.
+///
+/// `Spanned<T>` will accept this representation when deserializing, so
+/// wrapping it in a `WithSpan` gives a lossless round-trip. Use
+/// [`ExplicitSpans`] to do the same for every `Spanned` inside a larger value.
+/// Spans which end before they start are rejected with an error, so
+/// untrusted input can't produce an invalid [`Span`](crate::Span).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WithSpan<S>(pub S);
+
+impl<S> WithSpan<S> {
+    pub fn into_inner(self) -> S { self.0 }
+}
+
+impl<T: Serialize> Serialize for WithSpan<Spanned<T>> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        serialize_explicit(&self.0, ser)
+    }
+}
+
+impl<T: Serialize> Serialize for WithSpan<&Spanned<T>> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        serialize_explicit(self.0, ser)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for WithSpan<Spanned<T>> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_struct(NAME, FIELDS, Visitor(PhantomData))
+            .map(WithSpan)
+    }
+}
+
+fn serialize_explicit<T, S>(
+    spanned: &Spanned<T>,
+    ser: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let mut state = ser.serialize_struct(NAME, 3)?;
+    state.serialize_field(VALUE, spanned.value())?;
+    state.serialize_field(START, &spanned.start())?;
+    state.serialize_field(END, &spanned.end())?;
+    state.end()
+}
+
+/// A wrapper which makes every [`Spanned`] inside a value use the explicit
+/// representation from [`WithSpan`], both when serializing and when
+/// deserializing.
+///
+/// This lets a tree of `Spanned` values be sent between processes (e.g. as
+/// JSON or CBOR) and reconstructed with its spans intact.
+///
+/// The switch is tracked per-thread for the duration of the
+/// `serialize()`/`deserialize()` call, so formats which hand values off to
+/// other threads part-way through won't see it.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ExplicitSpans<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T> ExplicitSpans<T> {
+    pub fn into_inner(self) -> T { self.0 }
+}
+
+#[cfg(feature = "std")]
+impl<T: Serialize> Serialize for ExplicitSpans<T> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        explicitly(|| self.0.serialize(ser))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ExplicitSpans<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        explicitly(|| T::deserialize(de)).map(ExplicitSpans)
+    }
+}
+
+/// A wrapper which makes every [`Spanned`] inside a value ask for its span,
+/// even when the deserializer isn't human-readable.
+///
+/// Binary formats normally have no source text to point into, so `Spanned`
+/// skips the span protocol for them and uses an empty `0..0` span. Use this
+/// for binary formats which do record byte offsets.
+///
+/// Like [`ExplicitSpans`], the switch is tracked per-thread for the duration
+/// of the `deserialize()` call. Wrap the deserializer in
+/// [`HumanReadable`](crate::de::HumanReadable) instead when that isn't an
+/// option, or without the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BinarySpans<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T> BinarySpans<T> {
+    pub fn into_inner(self) -> T { self.0 }
+}
+
+#[cfg(feature = "std")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for BinarySpans<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        with_flag(&BINARY, || T::deserialize(de)).map(BinarySpans)
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static EXPLICIT: core::cell::Cell<bool> = core::cell::Cell::new(false);
+    static BINARY: core::cell::Cell<bool> = core::cell::Cell::new(false);
+}
+
+/// Should `Spanned` values use the explicit representation?
+#[cfg(feature = "std")]
+pub(crate) fn is_explicit() -> bool { EXPLICIT.with(|e| e.get()) }
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn is_explicit() -> bool { false }
+
+/// Should `Spanned` values use the span protocol with formats which aren't
+/// human-readable?
+#[cfg(feature = "std")]
+pub(crate) fn spans_in_binary() -> bool { BINARY.with(|b| b.get()) }
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn spans_in_binary() -> bool { false }
+
+#[cfg(feature = "std")]
+fn explicitly<R>(f: impl FnOnce() -> R) -> R { with_flag(&EXPLICIT, f) }
+
+#[cfg(feature = "std")]
+type Flag = std::thread::LocalKey<core::cell::Cell<bool>>;
+
+/// Set a thread-local flag while `f` runs, restoring it afterwards.
+#[cfg(feature = "std")]
+fn with_flag<R>(flag: &'static Flag, f: impl FnOnce() -> R) -> R {
+    struct Restore(&'static Flag, bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) { self.0.with(|e| e.set(self.1)); }
+    }
+
+    let _restore = Restore(flag, flag.with(|e| e.replace(true)));
+    f()
+}
@@ -0,0 +1,116 @@
+use serde::de::{value::Error, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde_spanned::{
+    de::{is_spanned, HumanReadable, SpannedDeserializer},
+    Spanned,
+};
+
+/// A binary format holding a sequence of integers, which records the byte
+/// range each integer came from.
+#[derive(Copy, Clone)]
+struct Ints<'a>(&'a [(i64, usize, usize)]);
+
+#[derive(Copy, Clone)]
+struct Int(i64, usize, usize);
+
+struct IntsSeq<'a>(core::slice::Iter<'a, (i64, usize, usize)>);
+
+impl<'de, 'a> Deserializer<'de> for Ints<'a> {
+    type Error = Error;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(IntsSeq(self.0.iter()))
+    }
+
+    fn is_human_readable(&self) -> bool { false }
+}
+
+impl<'de, 'a> SeqAccess<'de> for IntsSeq<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: serde::de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.0
+            .next()
+            .map(|&(value, start, end)| {
+                seed.deserialize(Int(value, start, end))
+            })
+            .transpose()
+    }
+}
+
+impl<'de> Deserializer<'de> for Int {
+    type Error = Error;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i64(self.0)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if is_spanned(name, fields) {
+            visitor.visit_map(SpannedDeserializer::new(self.1, self.2, self))
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn is_human_readable(&self) -> bool { false }
+}
+
+const INTS: Ints<'static> = Ints(&[(1, 1, 3), (23, 5, 8)]);
+
+fn spans(values: &[Spanned<i64>; 2]) -> [(i64, (usize, usize)); 2] {
+    [
+        (*values[0].value(), values[0].span()),
+        (*values[1].value(), values[1].span()),
+    ]
+}
+
+#[test]
+fn binary_formats_get_empty_spans() {
+    let got = <[Spanned<i64>; 2]>::deserialize(INTS).unwrap();
+
+    assert_eq!(spans(&got), [(1, (0, 0)), (23, (0, 0))]);
+}
+
+#[test]
+fn human_readable_asks_nested_values_for_spans() {
+    let got =
+        <[Spanned<i64>; 2]>::deserialize(HumanReadable::new(INTS)).unwrap();
+
+    assert_eq!(spans(&got), [(1, (1, 3)), (23, (5, 8))]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn binary_spans_asks_nested_values_for_spans() {
+    use serde_spanned::BinarySpans;
+
+    let got = BinarySpans::<[Spanned<i64>; 2]>::deserialize(INTS).unwrap();
+
+    assert_eq!(spans(&got.into_inner()), [(1, (1, 3)), (23, (5, 8))]);
+}
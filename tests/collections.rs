@@ -0,0 +1,133 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use serde_spanned::Spanned;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+fn text<'a, T>(src: &'a str, spanned: &Spanned<T>) -> &'a str {
+    let (start, end) = spanned.span();
+    &src[start..end]
+}
+
+#[test]
+fn equality_ignores_the_span() {
+    let a = Spanned::new(42, 0, 2);
+    let b = Spanned::new(42, 10, 12);
+
+    assert_eq!(a, b);
+    assert_eq!(a.cmp(&b), std::cmp::Ordering::Equal);
+    assert_ne!(a, Spanned::new(43, 0, 2));
+    assert!(Spanned::new(1, 50, 51) < Spanned::new(2, 0, 1));
+}
+
+#[test]
+fn vec_of_spanned() {
+    let src = "[1, 22, 333]";
+
+    for items in &[
+        common::from_str_tree::<Vec<Spanned<u32>>>(src).unwrap(),
+        common::from_str_streaming::<Vec<Spanned<u32>>>(src).unwrap(),
+    ] {
+        let text: Vec<_> = items.iter().map(|item| text(src, item)).collect();
+        assert_eq!(text, ["1", "22", "333"]);
+        let values: Vec<u32> = items.iter().map(|item| *item.value()).collect();
+        assert_eq!(values, [1, 22, 333]);
+        assert!(items.contains(&Spanned::new(22, 100, 102)));
+    }
+}
+
+#[test]
+fn hash_map_with_spanned_values() {
+    let src = r#"{"a": 1, "b": 22}"#;
+
+    for map in &[
+        common::from_str_tree::<HashMap<String, Spanned<u32>>>(src).unwrap(),
+        common::from_str_streaming::<HashMap<String, Spanned<u32>>>(src)
+            .unwrap(),
+    ] {
+        assert_eq!(text(src, &map["a"]), "1");
+        assert_eq!(text(src, &map["b"]), "22");
+    }
+}
+
+#[test]
+fn hash_map_with_spanned_keys() {
+    let src = r#"{"a": 1, "bb": 22}"#;
+
+    for map in &[
+        common::from_str_tree::<HashMap<Spanned<String>, Spanned<u32>>>(src)
+            .unwrap(),
+        common::from_str_streaming::<HashMap<Spanned<String>, Spanned<u32>>>(
+            src,
+        )
+        .unwrap(),
+    ] {
+        // Borrow<str> lets the map be indexed with a string literal
+        assert_eq!(*map["bb"].value(), 22);
+        assert!(map.contains_key("a"));
+        assert!(!map.contains_key("c"));
+
+        let (key, value) = map.get_key_value("bb").unwrap();
+        assert_eq!(text(src, key), r#""bb""#);
+        assert_eq!(text(src, value), "22");
+    }
+}
+
+#[test]
+fn hash_map_lookup_with_a_differently_spanned_key() {
+    let mut map = HashMap::new();
+    map.insert(Spanned::new(String::from("key"), 0, 5), 1);
+
+    assert_eq!(
+        map.get(&Spanned::new(String::from("key"), 10, 15)),
+        Some(&1)
+    );
+    assert_eq!(map.get(&String::from("key")), Some(&1));
+
+    // inserting an equal key replaces the value but keeps the original key
+    map.insert(Spanned::new(String::from("key"), 10, 15), 2);
+    assert_eq!(map.len(), 1);
+    let (key, value) = map.get_key_value("key").unwrap();
+    assert_eq!(key.span(), (0, 5));
+    assert_eq!(*value, 2);
+}
+
+#[test]
+fn btree_map_with_spanned_keys() {
+    let src = r#"{"b": 22, "a": 1}"#;
+
+    for map in &[
+        common::from_str_tree::<BTreeMap<Spanned<String>, Spanned<u32>>>(src)
+            .unwrap(),
+        common::from_str_streaming::<BTreeMap<Spanned<String>, Spanned<u32>>>(
+            src,
+        )
+        .unwrap(),
+    ] {
+        let keys: Vec<_> = map.keys().map(|key| text(src, key)).collect();
+        assert_eq!(keys, [r#""a""#, r#""b""#]);
+
+        assert_eq!(*map["a"].value(), 1);
+        assert_eq!(text(src, &map["b"]), "22");
+        assert!(map.contains_key(&String::from("a")));
+    }
+}
+
+#[test]
+fn sets_of_spanned() {
+    let src = r#"["x", "yy", "x"]"#;
+
+    let hash_set: HashSet<Spanned<String>> =
+        common::from_str_tree(src).unwrap();
+    assert_eq!(hash_set.len(), 2);
+    assert!(hash_set.contains("yy"));
+    // the first occurrence is kept
+    assert_eq!(hash_set.get("x").unwrap().span(), (1, 4));
+
+    let btree_set: BTreeSet<Spanned<String>> =
+        common::from_str_streaming(src).unwrap();
+    let items: Vec<_> = btree_set.iter().map(|item| text(src, item)).collect();
+    assert_eq!(items, [r#""x""#, r#""yy""#]);
+    assert!(btree_set.contains("x"));
+}
@@ -0,0 +1,661 @@
+//! A minimal span-aware format used by the integration tests.
+//!
+//! Documents look like JSON with integers only (`{"a": [1, true, null]}`).
+//! There are two deserializers for it:
+//!
+//! - [`from_str_tree()`] parses the whole document into [`Node`]s first and
+//!   reports spans with [`SpannedDeserializer`]
+//! - [`from_str_streaming()`] deserializes directly from the text and reports
+//!   spans with [`StreamingSpanned`]
+//!
+//! With the `test-util` feature, [`Tree`] and [`Streaming`] also implement
+//! the conformance suite's [`Format`](serde_spanned::conformance::Format),
+//! as a reference for other formats.
+
+#![allow(dead_code)]
+
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess,
+    IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde_spanned::de::{
+    is_spanned, Offset, SpannedDeserializer, StreamingSpanned,
+};
+use std::fmt::{self, Display, Formatter};
+
+/// An error message, with the offset of the value it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(pub String, pub Option<usize>);
+
+impl Error {
+    /// Say the error came from the value at `offset`, unless a value nested
+    /// inside it already has.
+    fn at(self, offset: usize) -> Self {
+        let Error(msg, at) = self;
+        Error(msg, at.or(Some(offset)))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { f.write_str(&self.0) }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self { Error(msg.to_string(), None) }
+}
+
+/// Deserialize by parsing into a tree of [`Node`]s first.
+pub fn from_str_tree<T: DeserializeOwned>(src: &str) -> Result<T, Error> {
+    let node = parse(src)?;
+    T::deserialize(&node)
+}
+
+/// Deserialize directly from the text.
+pub fn from_str_streaming<T: DeserializeOwned>(src: &str) -> Result<T, Error> {
+    let mut de = Stream::new(src);
+    let value = T::deserialize(&mut de)?;
+
+    if de.start_offset() != src.len() {
+        return Err(de.error("trailing characters"));
+    }
+
+    Ok(value)
+}
+
+/// The tree deserializer, for the conformance suite.
+pub struct Tree;
+
+/// The streaming deserializer, for the conformance suite.
+pub struct Streaming;
+
+#[cfg(feature = "test-util")]
+impl serde_spanned::conformance::Format for Tree {
+    type Error = Error;
+
+    fn render(&self, doc: &serde_spanned::conformance::Doc) -> String {
+        render(doc)
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, src: &str) -> Result<T, Error> {
+        from_str_tree(src)
+    }
+
+    fn error_offset(&self, _src: &str, error: &Error) -> Option<usize> {
+        error.1
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl serde_spanned::conformance::Format for Streaming {
+    type Error = Error;
+
+    fn render(&self, doc: &serde_spanned::conformance::Doc) -> String {
+        render(doc)
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, src: &str) -> Result<T, Error> {
+        from_str_streaming(src)
+    }
+
+    fn error_offset(&self, _src: &str, error: &Error) -> Option<usize> {
+        error.1
+    }
+}
+
+/// Write a conformance document in this format, with some whitespace so
+/// spans which include it are caught.
+#[cfg(feature = "test-util")]
+pub fn render(doc: &serde_spanned::conformance::Doc) -> String {
+    use serde_spanned::conformance::Doc;
+
+    match doc {
+        Doc::Bool(b) => b.to_string(),
+        Doc::Integer(n) => n.to_string(),
+        Doc::String(s) => format!("{:?}", s),
+        Doc::Seq(items) => {
+            let items: Vec<_> = items.iter().map(render).collect();
+            format!("[ {} ]", items.join(", "))
+        },
+        Doc::Map(entries) => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(key, value)| format!("{:?}: {}", key, render(value)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: Kind,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Seq(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+}
+
+pub fn parse(src: &str) -> Result<Node, Error> {
+    let mut stream = Stream::new(src);
+    let node = stream.node()?;
+
+    if stream.start_offset() != src.len() {
+        return Err(stream.error("trailing characters"));
+    }
+
+    Ok(node)
+}
+
+/// The text of a document, with the position of the next character.
+pub struct Stream<'a> {
+    src: &'a str,
+    pos: usize,
+    last_end: usize,
+}
+
+impl<'a> Stream<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Stream {
+            src,
+            pos: 0,
+            last_end: 0,
+        }
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error(format!("{} at offset {}", msg, self.pos), Some(self.pos))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), Error> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            self.last_end = self.pos;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", c as char)))
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Kind, Error> {
+        let rest = &self.src[self.pos..];
+
+        let (kind, len) = if let Some(string) = rest.strip_prefix('"') {
+            let len = string
+                .find('"')
+                .ok_or_else(|| self.error("unterminated string"))?;
+            (Kind::String(string[..len].to_string()), len + 2)
+        } else if rest.starts_with("null") {
+            (Kind::Null, 4)
+        } else if rest.starts_with("true") {
+            (Kind::Bool(true), 4)
+        } else if rest.starts_with("false") {
+            (Kind::Bool(false), 5)
+        } else {
+            let len = rest
+                .char_indices()
+                .find(|&(i, c)| !(c.is_ascii_digit() || i == 0 && c == '-'))
+                .map_or(rest.len(), |(i, _)| i);
+            let n = rest[..len]
+                .parse()
+                .map_err(|_| self.error("expected a value"))?;
+            (Kind::Integer(n), len)
+        };
+
+        self.pos += len;
+        self.last_end = self.pos;
+        Ok(kind)
+    }
+
+    /// Run `f`, blaming any errors without an offset on the next value.
+    fn located<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let start = self.start_offset();
+        f(self).map_err(|e| e.at(start))
+    }
+
+    fn node(&mut self) -> Result<Node, Error> {
+        let start = self.start_offset();
+
+        let kind = match self.peek() {
+            Some(b'[') => {
+                self.expect(b'[')?;
+                let mut items = Vec::new();
+                while self.peek() != Some(b']') {
+                    if !items.is_empty() {
+                        self.expect(b',')?;
+                    }
+                    items.push(self.node()?);
+                }
+                self.expect(b']')?;
+                Kind::Seq(items)
+            },
+            Some(b'{') => {
+                self.expect(b'{')?;
+                let mut entries = Vec::new();
+                while self.peek() != Some(b'}') {
+                    if !entries.is_empty() {
+                        self.expect(b',')?;
+                    }
+                    let key = self.node()?;
+                    self.expect(b':')?;
+                    entries.push((key, self.node()?));
+                }
+                self.expect(b'}')?;
+                Kind::Map(entries)
+            },
+            _ => self.scalar()?,
+        };
+
+        Ok(Node {
+            kind,
+            start,
+            end: self.end_offset(),
+        })
+    }
+}
+
+impl Offset for Stream<'_> {
+    fn start_offset(&mut self) -> usize {
+        self.skip_whitespace();
+        self.pos
+    }
+
+    fn end_offset(&self) -> usize { self.last_end }
+}
+
+impl<'de> Deserializer<'de> for &Node {
+    type Error = Error;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier
+        ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let result = match &self.kind {
+            Kind::Null => visitor.visit_unit(),
+            Kind::Bool(b) => visitor.visit_bool(*b),
+            Kind::Integer(n) => visitor.visit_i64(*n),
+            Kind::String(s) => visitor.visit_str(s),
+            Kind::Seq(items) => visitor.visit_seq(NodeSeq(items.iter())),
+            Kind::Map(entries) => visitor.visit_map(NodeMap {
+                entries: entries.iter(),
+                value: None,
+            }),
+        };
+        result.map_err(|e| e.at(self.start))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let result = match self.kind {
+            Kind::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        };
+        result.map_err(|e| e.at(self.start))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor
+            .visit_newtype_struct(self)
+            .map_err(|e| e.at(self.start))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if is_spanned(name, fields) {
+            visitor
+                .visit_map(SpannedDeserializer::new(self.start, self.end, self))
+                .map_err(|e| e.at(self.start))
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let result = match &self.kind {
+            Kind::String(variant) => {
+                visitor.visit_enum(variant.as_str().into_deserializer())
+            },
+            Kind::Map(entries) if entries.len() == 1 => {
+                visitor.visit_enum(NodeEnum(&entries[0]))
+            },
+            _ => Err(Error(String::from("expected an enum"), None)),
+        };
+        result.map_err(|e| e.at(self.start))
+    }
+}
+
+struct NodeSeq<'a>(std::slice::Iter<'a, Node>);
+
+impl<'de, 'a> SeqAccess<'de> for NodeSeq<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.0.next().map(|node| seed.deserialize(node)).transpose()
+    }
+}
+
+struct NodeMap<'a> {
+    entries: std::slice::Iter<'a, (Node, Node)>,
+    value: Option<&'a Node>,
+}
+
+impl<'de, 'a> MapAccess<'de> for NodeMap<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Error> {
+        let value = self.value.take().expect("next_key_seed wasn't called");
+        seed.deserialize(value)
+    }
+}
+
+struct NodeEnum<'a>(&'a (Node, Node));
+
+impl<'de, 'a> EnumAccess<'de> for NodeEnum<'a> {
+    type Error = Error;
+    type Variant = &'a Node;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, &'a Node), Error> {
+        let (variant, value) = self.0;
+        Ok((seed.deserialize(variant)?, value))
+    }
+}
+
+impl<'de> VariantAccess<'de> for &Node {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        <serde::de::IgnoredAny as serde::Deserialize>::deserialize(self)
+            .map(|_| ())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+}
+
+impl<'de, 'a, 'b> Deserializer<'de> for &'b mut Stream<'a> {
+    type Error = Error;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier
+        ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.located(|de| match de.peek() {
+            Some(b'[') => {
+                de.expect(b'[')?;
+                let value = visitor.visit_seq(StreamSeq {
+                    de: &mut *de,
+                    first: true,
+                })?;
+                de.expect(b']')?;
+                Ok(value)
+            },
+            Some(b'{') => {
+                de.expect(b'{')?;
+                let value = visitor.visit_map(StreamMap {
+                    de: &mut *de,
+                    first: true,
+                })?;
+                de.expect(b'}')?;
+                Ok(value)
+            },
+            _ => match de.scalar()? {
+                Kind::Null => visitor.visit_unit(),
+                Kind::Bool(b) => visitor.visit_bool(b),
+                Kind::Integer(n) => visitor.visit_i64(n),
+                Kind::String(s) => visitor.visit_string(s),
+                Kind::Seq(_) | Kind::Map(_) => unreachable!(),
+            },
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.skip_whitespace();
+
+        if self.src[self.pos..].starts_with("null") {
+            self.scalar()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if is_spanned(name, fields) {
+            self.located(|de| visitor.visit_map(StreamingSpanned::new(de)))
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.located(|de| {
+            if de.peek() == Some(b'{') {
+                de.expect(b'{')?;
+                let value = visitor.visit_enum(StreamEnum(&mut *de))?;
+                de.expect(b'}')?;
+                Ok(value)
+            } else {
+                match de.scalar()? {
+                    Kind::String(variant) => {
+                        visitor.visit_enum(variant.into_deserializer())
+                    },
+                    _ => Err(de.error("expected an enum")),
+                }
+            }
+        })
+    }
+}
+
+struct StreamSeq<'a, 'b> {
+    de: &'b mut Stream<'a>,
+    first: bool,
+}
+
+impl<'de, 'a, 'b> SeqAccess<'de> for StreamSeq<'a, 'b> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.de.peek() == Some(b']') {
+            return Ok(None);
+        }
+        if !std::mem::replace(&mut self.first, false) {
+            self.de.expect(b',')?;
+        }
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct StreamMap<'a, 'b> {
+    de: &'b mut Stream<'a>,
+    first: bool,
+}
+
+impl<'de, 'a, 'b> MapAccess<'de> for StreamMap<'a, 'b> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.de.peek() == Some(b'}') {
+            return Ok(None);
+        }
+        if !std::mem::replace(&mut self.first, false) {
+            self.de.expect(b',')?;
+        }
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Error> {
+        self.de.expect(b':')?;
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct StreamEnum<'a, 'b>(&'b mut Stream<'a>);
+
+impl<'de, 'a, 'b> EnumAccess<'de> for StreamEnum<'a, 'b> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(&mut *self.0)?;
+        self.0.expect(b':')?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, 'b> VariantAccess<'de> for StreamEnum<'a, 'b> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        <serde::de::IgnoredAny as serde::Deserialize>::deserialize(self.0)
+            .map(|_| ())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Error> {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.0.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.0.deserialize_map(visitor)
+    }
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "test-util")]
+
+mod common;
+
+use serde_spanned::conformance::{assert_conformance, run};
+
+#[test]
+fn tree_deserializer_conforms() { assert_conformance(&common::Tree); }
+
+#[test]
+fn streaming_deserializer_conforms() { assert_conformance(&common::Streaming); }
+
+#[test]
+fn failures_are_reported_by_check() {
+    struct Spanless;
+
+    impl serde_spanned::conformance::Format for Spanless {
+        type Error = serde::de::value::Error;
+
+        fn render(&self, doc: &serde_spanned::conformance::Doc) -> String {
+            common::render(doc)
+        }
+
+        fn deserialize<T: serde::de::DeserializeOwned>(
+            &self,
+            _src: &str,
+        ) -> Result<T, Self::Error> {
+            Err(serde::de::Error::custom("not supported"))
+        }
+
+        fn error_offset(
+            &self,
+            _src: &str,
+            _error: &Self::Error,
+        ) -> Option<usize> {
+            None
+        }
+    }
+
+    let failures = run(&Spanless);
+
+    assert_eq!(failures.len(), 13);
+    assert!(failures.iter().all(|f| f.message.contains("not supported")));
+    let unlocated: Vec<_> = failures
+        .iter()
+        .filter(|f| f.message.contains("doesn't say where it is"))
+        .map(|f| f.check)
+        .collect();
+    assert_eq!(
+        unlocated,
+        ["errors", "errors_in_maps", "errors_in_sequences"]
+    );
+}
+
+#[test]
+fn errors_must_point_at_the_bad_value() {
+    struct Misplaced;
+
+    impl serde_spanned::conformance::Format for Misplaced {
+        type Error = common::Error;
+
+        fn render(&self, doc: &serde_spanned::conformance::Doc) -> String {
+            common::render(doc)
+        }
+
+        fn deserialize<T: serde::de::DeserializeOwned>(
+            &self,
+            src: &str,
+        ) -> Result<T, Self::Error> {
+            common::from_str_tree(src)
+        }
+
+        fn error_offset(
+            &self,
+            _src: &str,
+            _error: &Self::Error,
+        ) -> Option<usize> {
+            Some(0)
+        }
+    }
+
+    let failures = run(&Misplaced);
+
+    let checks: Vec<_> = failures.iter().map(|f| f.check).collect();
+    assert_eq!(checks, ["errors", "errors_in_maps", "errors_in_sequences"]);
+    assert!(failures[0]
+        .message
+        .contains("points at 0 instead of \"oops\""));
+}
+
+#[test]
+fn panicking_checks_are_failures() {
+    struct Panicky;
+
+    impl serde_spanned::conformance::Format for Panicky {
+        type Error = common::Error;
+
+        fn render(&self, doc: &serde_spanned::conformance::Doc) -> String {
+            common::render(doc)
+        }
+
+        fn deserialize<T: serde::de::DeserializeOwned>(
+            &self,
+            _src: &str,
+        ) -> Result<T, Self::Error> {
+            panic!("unimplemented")
+        }
+
+        fn error_offset(
+            &self,
+            _src: &str,
+            _error: &Self::Error,
+        ) -> Option<usize> {
+            None
+        }
+    }
+
+    let failures = run(&Panicky);
+
+    assert_eq!(failures.len(), 13);
+    assert!(failures.iter().all(|f| f.message == "the check panicked"));
+}
@@ -0,0 +1,145 @@
+#![cfg(feature = "std")]
+
+use serde_spanned::{content_hash, ContentHash, Spanned};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::Hasher,
+};
+
+fn serialized<T: serde::Serialize>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content_hash(value, &mut hasher).unwrap();
+    hasher.finish()
+}
+
+fn derived<T: ContentHash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.content_hash(&mut hasher);
+    hasher.finish()
+}
+
+fn map(offset: usize) -> HashMap<String, Spanned<u32>> {
+    (0..50)
+        .map(|i| {
+            let value = Spanned::new(i, offset + i as usize, offset + 100);
+            (format!("key-{}", i), value)
+        })
+        .collect()
+}
+
+#[test]
+fn equal_hash_maps_hash_the_same() {
+    // every HashMap has its own random seed, so iteration order differs
+    let maps: Vec<_> = (0..20).map(map).collect();
+
+    for map in &maps[1..] {
+        assert_eq!(serialized(map), serialized(&maps[0]));
+        assert_eq!(derived(map), derived(&maps[0]));
+    }
+}
+
+#[test]
+fn different_maps_hash_differently() {
+    let mut other = map(0);
+    other.insert(String::from("key-0"), Spanned::new(1000, 0, 0));
+
+    assert_ne!(serialized(&other), serialized(&map(0)));
+    assert_ne!(derived(&other), derived(&map(0)));
+}
+
+#[test]
+fn entries_are_hashed_as_pairs() {
+    let mut first = HashMap::new();
+    first.insert("a", 1_u8);
+    first.insert("b", 2);
+    let mut second = HashMap::new();
+    second.insert("a", 2_u8);
+    second.insert("b", 1);
+
+    assert_ne!(serialized(&first), serialized(&second));
+}
+
+#[test]
+fn equal_hash_sets_hash_the_same() {
+    let sets: Vec<HashSet<u32>> = (0..20).map(|_| (0..50).collect()).collect();
+
+    for set in &sets[1..] {
+        assert_eq!(derived(set), derived(&sets[0]));
+    }
+}
+
+#[test]
+fn integers_hash_the_same_regardless_of_type() {
+    assert_eq!(serialized(&5_i64), serialized(&5_u64));
+    assert_eq!(serialized(&5_i8), serialized(&5_u128));
+    assert_eq!(derived(&5_i32), derived(&5_u16));
+    assert_ne!(serialized(&-5_i64), serialized(&5_u64));
+}
+
+#[test]
+fn spans_are_ignored() {
+    let first = vec![Spanned::new("a", 0, 3), Spanned::new("b", 4, 7)];
+    let second = vec![Spanned::new("a", 10, 13), Spanned::new("b", 20, 27)];
+
+    assert_eq!(serialized(&first), serialized(&second));
+    assert_eq!(derived(&first), derived(&second));
+    assert_ne!(derived(&first), derived(&vec![Spanned::new("b", 0, 3)]));
+}
+
+#[cfg(feature = "derive")]
+mod derive {
+    use super::derived;
+    use serde_spanned::{ContentHash, Spanned};
+    use std::collections::BTreeMap;
+
+    #[derive(ContentHash)]
+    struct Config {
+        name: Spanned<String>,
+        ports: Vec<Spanned<u16>>,
+        env: BTreeMap<String, Spanned<String>>,
+        #[walk_spans(skip)]
+        #[allow(dead_code)]
+        cache: Option<u64>,
+        mode: Mode,
+    }
+
+    #[derive(ContentHash)]
+    enum Mode {
+        Fast,
+        Slow { delay: Spanned<u32> },
+    }
+
+    fn config(offset: usize, cache: Option<u64>) -> Config {
+        let mut env = BTreeMap::new();
+        env.insert(
+            String::from("HOME"),
+            Spanned::new(String::from("/root"), offset, offset + 7),
+        );
+
+        Config {
+            name: Spanned::new(String::from("app"), offset, offset + 5),
+            ports: vec![Spanned::new(80, offset + 1, offset + 3)],
+            env,
+            cache,
+            mode: Mode::Slow {
+                delay: Spanned::new(5, offset + 2, offset + 3),
+            },
+        }
+    }
+
+    #[test]
+    fn derived_hashes_ignore_spans_and_skipped_fields() {
+        assert_eq!(derived(&config(0, None)), derived(&config(42, Some(7))));
+    }
+
+    #[test]
+    fn derived_hashes_see_every_field() {
+        let mut other = config(0, None);
+        other.mode = Mode::Fast;
+        assert_ne!(derived(&other), derived(&config(0, None)));
+
+        let mut other = config(0, None);
+        other.ports.push(Spanned::new(443, 0, 0));
+        assert_ne!(derived(&other), derived(&config(0, None)));
+    }
+}
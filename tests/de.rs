@@ -0,0 +1,320 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use serde::de::{Deserialize, Deserializer, Error, MapAccess, Visitor};
+use serde_derive::Deserialize;
+use serde_spanned::{Spanned, END, FIELDS, NAME, START, VALUE};
+use std::fmt::{self, Formatter};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Document {
+    name: Spanned<String>,
+    items: Spanned<Vec<Spanned<i64>>>,
+    nested: Option<Spanned<Nested>>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Nested {
+    enabled: Spanned<bool>,
+}
+
+const SRC: &str =
+    r#"{"name": "example", "items": [1, 23], "nested": {"enabled": true}}"#;
+
+fn text<'a, T>(spanned: &Spanned<T>) -> &'a str {
+    let (start, end) = spanned.span();
+    &SRC[start..end]
+}
+
+fn check_document(doc: &Document) {
+    assert_eq!(doc.name.value(), "example");
+    assert_eq!(text(&doc.name), "\"example\"");
+    assert_eq!(text(&doc.items), "[1, 23]");
+    let items: Vec<_> = doc.items.value().iter().map(text).collect();
+    assert_eq!(items, ["1", "23"]);
+
+    let nested = doc.nested.as_ref().unwrap();
+    assert_eq!(text(nested), r#"{"enabled": true}"#);
+    assert!(*nested.value().enabled.value());
+    assert_eq!(text(&nested.value().enabled), "true");
+}
+
+#[test]
+fn spans_from_the_spanned_deserializer() {
+    let doc: Document = common::from_str_tree(SRC).unwrap();
+
+    check_document(&doc);
+}
+
+#[test]
+fn spans_from_the_streaming_deserializer() {
+    let doc: Document = common::from_str_streaming(SRC).unwrap();
+
+    check_document(&doc);
+}
+
+#[test]
+fn both_deserializers_agree() {
+    let src = r#"  [ {"enabled": false} , {"enabled":true}]  "#;
+
+    let tree: Vec<Spanned<Nested>> = common::from_str_tree(src).unwrap();
+    let streaming: Vec<Spanned<Nested>> =
+        common::from_str_streaming(src).unwrap();
+
+    assert_eq!(tree, streaming);
+    let spans: Vec<_> = tree.iter().map(Spanned::span).collect();
+    let spans_streaming: Vec<_> = streaming.iter().map(Spanned::span).collect();
+    assert_eq!(spans, spans_streaming);
+    let text: Vec<_> =
+        spans.iter().map(|&(start, end)| &src[start..end]).collect();
+    assert_eq!(text, [r#"{"enabled": false}"#, r#"{"enabled":true}"#]);
+}
+
+#[test]
+fn errors_inside_a_spanned_value_are_propagated() {
+    let src = r#"{"enabled": 42}"#;
+
+    assert!(common::from_str_tree::<Spanned<Nested>>(src).is_err());
+    assert!(common::from_str_streaming::<Spanned<Nested>>(src).is_err());
+}
+
+/// A span type which, like `toml::Spanned`, requires the keys in the order
+/// they appear in [`FIELDS`].
+#[derive(Debug, PartialEq)]
+struct Strict<T> {
+    start: usize,
+    end: usize,
+    value: T,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Strict<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        struct StrictVisitor<T>(std::marker::PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for StrictVisitor<T> {
+            type Value = Strict<T>;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("a spanned value")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Strict<T>, A::Error> {
+                if map.next_key::<String>()?.as_deref() != Some(START) {
+                    return Err(Error::custom("spanned start key not found"));
+                }
+                let start = map.next_value()?;
+
+                if map.next_key::<String>()?.as_deref() != Some(END) {
+                    return Err(Error::custom("spanned end key not found"));
+                }
+                let end = map.next_value()?;
+
+                if map.next_key::<String>()?.as_deref() != Some(VALUE) {
+                    return Err(Error::custom("spanned value key not found"));
+                }
+                let value = map.next_value()?;
+
+                Ok(Strict { start, end, value })
+            }
+        }
+
+        de.deserialize_struct(NAME, FIELDS, StrictVisitor(Default::default()))
+    }
+}
+
+#[test]
+fn spanned_deserializer_yields_keys_in_protocol_order() {
+    let got: Strict<i64> = common::from_str_tree(" 42 ").unwrap();
+
+    assert_eq!(
+        got,
+        Strict {
+            start: 1,
+            end: 3,
+            value: 42
+        }
+    );
+}
+
+#[test]
+fn streaming_spanned_is_incompatible_with_strict_visitors() {
+    let err = common::from_str_streaming::<Strict<i64>>(" 42 ").unwrap_err();
+
+    assert_eq!(err.0, "spanned end key not found");
+}
+
+/// Deserialize `src` with both the tree and streaming deserializers.
+fn both<T: serde::de::DeserializeOwned>(
+    src: &str,
+) -> Vec<Result<T, common::Error>> {
+    vec![common::from_str_tree(src), common::from_str_streaming(src)]
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Optional {
+    port: Option<Spanned<i64>>,
+}
+
+#[test]
+fn missing_optional_fields_are_none() {
+    for result in both::<Optional>("{}") {
+        assert_eq!(result.unwrap().port, None);
+    }
+}
+
+#[test]
+fn null_optional_fields_are_none() {
+    for result in both::<Optional>(r#"{"port": null}"#) {
+        assert_eq!(result.unwrap().port, None);
+    }
+}
+
+#[test]
+fn present_optional_fields_are_spanned() {
+    let src = r#"{"port": 8080}"#;
+
+    for result in both::<Optional>(src) {
+        let port = result.unwrap().port.unwrap();
+        assert_eq!(&src[port.start()..port.end()], "8080");
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "type")]
+enum Internal {
+    Circle { radius: i64 },
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "type")]
+enum InternalSpanned {
+    Circle { radius: Spanned<i64> },
+}
+
+#[test]
+fn spanned_around_an_internally_tagged_enum() {
+    let src = r#"[{"type": "Circle", "radius": 5}]"#;
+
+    for result in both::<Vec<Spanned<Internal>>>(src) {
+        let shapes = result.unwrap();
+        assert_eq!(shapes[0].value(), &Internal::Circle { radius: 5 });
+        let (start, end) = shapes[0].span();
+        assert_eq!(&src[start..end], r#"{"type": "Circle", "radius": 5}"#);
+    }
+}
+
+#[test]
+fn spanned_fields_inside_an_internally_tagged_enum_are_buffered() {
+    let src = r#"{"type": "Circle", "radius": 5}"#;
+
+    for result in both::<InternalSpanned>(src) {
+        assert!(result.is_err());
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "t", content = "c")]
+enum Adjacent {
+    Circle { radius: Spanned<i64> },
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "t", content = "c")]
+enum AdjacentPlain {
+    Circle { radius: i64 },
+}
+
+#[test]
+fn spanned_fields_inside_adjacently_tagged_content() {
+    let src = r#"{"t": "Circle", "c": {"radius": 5}}"#;
+
+    for result in both::<Adjacent>(src) {
+        let Adjacent::Circle { radius } = result.unwrap();
+        assert_eq!(&src[radius.start()..radius.end()], "5");
+    }
+}
+
+#[test]
+fn adjacently_tagged_content_before_the_tag_is_buffered() {
+    let src = r#"{"c": {"radius": 5}, "t": "Circle"}"#;
+
+    for result in both::<Adjacent>(src) {
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+fn spanned_around_an_adjacently_tagged_enum() {
+    let tag_first = r#"{"t": "Circle", "c": {"radius": 5}}"#;
+    let content_first = r#"{"c": {"radius": 5}, "t": "Circle"}"#;
+
+    for src in &[tag_first, content_first] {
+        for result in both::<Spanned<AdjacentPlain>>(src) {
+            let shape = result.unwrap();
+            assert_eq!(shape.value(), &AdjacentPlain::Circle { radius: 5 });
+            assert_eq!(shape.span(), (0, src.len()));
+        }
+    }
+}
+
+/// A self-describing value, which deserializes itself with
+/// `deserialize_any()` like `serde_json::Value`.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Any {
+    Null(()),
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Seq(Vec<Any>),
+    Map(std::collections::BTreeMap<String, Any>),
+}
+
+#[test]
+fn spanned_around_self_describing_values() {
+    let src = r#"{"values": [null, true, 1, "s", [2], {"a": 3}]}"#;
+
+    for result in
+        both::<std::collections::BTreeMap<String, Vec<Spanned<Any>>>>(src)
+    {
+        let values = result.unwrap();
+        let text: Vec<_> = values["values"]
+            .iter()
+            .map(|value| &src[value.start()..value.end()])
+            .collect();
+
+        assert_eq!(text, ["null", "true", "1", r#""s""#, "[2]", r#"{"a": 3}"#]);
+        assert_eq!(
+            values["values"][5].value(),
+            &Any::Map(
+                vec![(String::from("a"), Any::Integer(3))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+    }
+}
+
+#[test]
+fn spans_ending_at_usize_max_are_rejected() {
+    use serde::de::value::{Error, MapDeserializer};
+
+    let max = usize::MAX as u64;
+    let entries = vec![(START, max), (END, max), (VALUE, 1)];
+    let de = MapDeserializer::<_, Error>::new(entries.into_iter());
+
+    let err = Spanned::<u64>::deserialize(de).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "the span {}..{} ends before it starts",
+            usize::MAX,
+            usize::MAX - 1
+        )
+    );
+}
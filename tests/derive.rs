@@ -0,0 +1,392 @@
+#![cfg(all(feature = "derive", feature = "test-util"))]
+
+mod common;
+
+use serde::Deserialize;
+use serde_spanned::{
+    meta::FieldMeta, walk::Path, ContentHash, FieldsMeta, Respan, SpanMap,
+    Spanned, SpannedFields, Unspanned, WalkSpans,
+};
+use std::collections::BTreeMap;
+
+/// Every path visited by [`WalkSpans`], with the text its span covers.
+fn walk<'a, W: WalkSpans>(value: &W, src: &'a str) -> Vec<(String, &'a str)> {
+    let mut spans = Vec::new();
+    value.walk_spans(&Path::ROOT, &mut |path, (start, end)| {
+        spans.push((path.to_string(), &src[start..end]));
+    });
+    spans
+}
+
+/// Every path visited by [`WalkSpans`], for values which were built by hand.
+fn paths<W: WalkSpans>(value: &W) -> Vec<String> {
+    let mut paths = Vec::new();
+    value.walk_spans(&Path::ROOT, &mut |path, _| paths.push(path.to_string()));
+    paths
+}
+
+#[derive(Debug, Clone, PartialEq, SpannedFields)]
+#[spanned_fields(derive(Debug, PartialEq, WalkSpans))]
+#[serde(rename_all = "kebab-case")]
+struct Config {
+    #[serde(alias = "title")]
+    name: String,
+    max_connections: u32,
+    #[spanned_fields(nested)]
+    backends: Vec<Backend>,
+    #[spanned_fields(nested = "Option<TlsSpans>")]
+    #[serde(default)]
+    tls: Option<Tls>,
+}
+
+#[derive(Debug, Clone, PartialEq, SpannedFields)]
+#[spanned_fields(derive(Debug, PartialEq, WalkSpans))]
+struct Backend {
+    #[serde(rename = "address")]
+    host: String,
+    port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, SpannedFields)]
+#[spanned_fields(name = "TlsSpans", derive(Debug, PartialEq, WalkSpans))]
+struct Tls {
+    cert: String,
+}
+
+const CONFIG: &str = r#"{
+    "title": "example",
+    "max-connections": 10,
+    "backends": [{"address": "a", "port": 80}],
+    "tls": {"cert": "cert.pem"}
+}"#;
+
+fn plain_config() -> Config {
+    Config {
+        name: String::from("example"),
+        max_connections: 10,
+        backends: vec![Backend {
+            host: String::from("a"),
+            port: 80,
+        }],
+        tls: Some(Tls {
+            cert: String::from("cert.pem"),
+        }),
+    }
+}
+
+#[test]
+fn companion_struct_uses_serde_names_and_aliases() {
+    let tree: SpannedConfig = common::from_str_tree(CONFIG).unwrap();
+    let streaming: SpannedConfig = common::from_str_streaming(CONFIG).unwrap();
+
+    assert_eq!(tree, streaming);
+    assert_eq!(tree.name.value(), "example");
+    assert_eq!(*tree.max_connections.value(), 10);
+    let _: &Spanned<Option<TlsSpans>> = &tree.tls;
+}
+
+#[test]
+fn walk_companion_struct() {
+    let config: SpannedConfig = common::from_str_tree(CONFIG).unwrap();
+
+    let got = walk(&config, CONFIG);
+
+    assert_eq!(
+        got,
+        vec![
+            (String::from("name"), r#""example""#),
+            (String::from("max-connections"), "10"),
+            (
+                String::from("backends"),
+                r#"[{"address": "a", "port": 80}]"#
+            ),
+            (String::from("backends[0].address"), r#""a""#),
+            (String::from("backends[0].port"), "80"),
+            (String::from("tls"), r#"{"cert": "cert.pem"}"#),
+            (String::from("tls.cert"), r#""cert.pem""#),
+        ]
+    );
+}
+
+#[test]
+fn companion_struct_round_trips() {
+    let config: SpannedConfig = common::from_str_tree(CONFIG).unwrap();
+
+    let plain = Config::from(config);
+    assert_eq!(plain, plain_config());
+
+    let respanned = SpannedConfig::from(plain.clone());
+    assert_eq!(respanned.name.span(), (0, 0));
+    assert_eq!(respanned.backends.value()[0].port.span(), (0, 0));
+    assert_eq!(respanned.into_plain(), plain);
+}
+
+#[test]
+fn respan_companion_struct_from_a_span_map() {
+    let config: SpannedConfig = common::from_str_tree(CONFIG).unwrap();
+    let spans = SpanMap::record(&config);
+
+    let respanned: SpannedConfig = spans.respan(plain_config());
+
+    assert_eq!(walk(&respanned, CONFIG), walk(&config, CONFIG));
+}
+
+#[test]
+fn missing_nested_option_uses_the_default() {
+    let src = r#"{"name": "x", "max-connections": 1, "backends": []}"#;
+
+    let config: SpannedConfig = common::from_str_tree(src).unwrap();
+
+    assert!(config.tls.value().is_none());
+    assert_eq!(Config::from(config).tls, None);
+}
+
+#[derive(Debug, Clone, PartialEq, SpannedFields)]
+#[spanned_fields(derive(Debug))]
+struct Pair<T>(T, T);
+
+#[test]
+fn generic_tuple_companion_struct() {
+    let src = "[1, 2]";
+
+    let pair: SpannedPair<u8> = common::from_str_tree(src).unwrap();
+
+    assert_eq!(pair.0.span(), (1, 2));
+    assert_eq!(pair.1.span(), (4, 5));
+    assert_eq!(Pair::from(pair), Pair(1, 2));
+}
+
+#[derive(Debug, PartialEq)]
+enum Shape {
+    Circle { radius: u32 },
+    Rect(u32, u32),
+    Square(u32),
+    Point,
+}
+
+#[derive(Debug, PartialEq, Deserialize, WalkSpans, Unspanned, Respan)]
+#[unspanned(plain = "Shape")]
+#[serde(rename_all = "snake_case")]
+enum SpannedShape {
+    Circle {
+        #[serde(rename = "r")]
+        radius: Spanned<u32>,
+    },
+    Rect(Spanned<u32>, Spanned<u32>),
+    Square(Spanned<u32>),
+    Point,
+}
+
+#[test]
+fn walk_externally_tagged_enum() {
+    let src =
+        r#"[{"circle": {"r": 5}}, {"rect": [1, 2]}, {"square": 3}, "point"]"#;
+    let shapes: Vec<SpannedShape> = common::from_str_tree(src).unwrap();
+
+    let got = walk(&shapes, src);
+
+    assert_eq!(
+        got,
+        vec![
+            (String::from("[0].circle.r"), "5"),
+            (String::from("[1].rect[0]"), "1"),
+            (String::from("[1].rect[1]"), "2"),
+            (String::from("[2].square"), "3"),
+        ]
+    );
+}
+
+#[test]
+fn enum_round_trips() {
+    let src = r#"[{"circle": {"r": 5}}, {"rect": [1, 2]}, "point"]"#;
+    let shapes: Vec<SpannedShape> = common::from_str_streaming(src).unwrap();
+    let spans = SpanMap::record(&shapes);
+
+    let plain = shapes.into_plain();
+    assert_eq!(
+        plain,
+        vec![Shape::Circle { radius: 5 }, Shape::Rect(1, 2), Shape::Point]
+    );
+
+    let respanned: Vec<SpannedShape> = spans.respan(plain);
+    assert_eq!(
+        walk(&respanned, src),
+        vec![
+            (String::from("[0].circle.r"), "5"),
+            (String::from("[1].rect[0]"), "1"),
+            (String::from("[1].rect[1]"), "2"),
+        ]
+    );
+}
+
+#[derive(WalkSpans)]
+#[serde(tag = "type")]
+enum Internal {
+    Circle { radius: Spanned<u32> },
+}
+
+#[derive(WalkSpans)]
+#[serde(tag = "t", content = "c")]
+enum Adjacent {
+    Circle { radius: Spanned<u32> },
+}
+
+#[derive(WalkSpans)]
+#[serde(untagged)]
+enum Untagged {
+    Circle { radius: Spanned<u32> },
+}
+
+#[test]
+fn variant_segments_follow_the_enum_representation() {
+    let radius = Spanned::new(1, 0, 1);
+
+    assert_eq!(paths(&Internal::Circle { radius }), ["radius"]);
+    assert_eq!(paths(&Adjacent::Circle { radius }), ["c.radius"]);
+    assert_eq!(paths(&Untagged::Circle { radius }), ["radius"]);
+}
+
+#[derive(WalkSpans)]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+enum Renamed {
+    HttpServer(Spanned<u32>),
+    #[serde(rename = "other")]
+    Other(Spanned<u32>),
+}
+
+#[test]
+fn variant_segments_are_renamed() {
+    let value = Spanned::new(1, 0, 1);
+
+    assert_eq!(paths(&Renamed::HttpServer(value)), ["HTTP-SERVER"]);
+    assert_eq!(paths(&Renamed::Other(value)), ["other"]);
+}
+
+struct NotWalkable;
+
+#[derive(WalkSpans)]
+#[serde(rename_all = "camelCase")]
+struct Outer {
+    #[serde(flatten)]
+    inner: Inner,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    cache: Spanned<u32>,
+    #[walk_spans(skip)]
+    #[allow(dead_code)]
+    handle: NotWalkable,
+    retry_count: Spanned<u32>,
+}
+
+#[derive(WalkSpans)]
+struct Inner {
+    r#type: Spanned<u32>,
+}
+
+#[test]
+fn walk_flattened_and_skipped_fields() {
+    let outer = Outer {
+        inner: Inner {
+            r#type: Spanned::new(1, 0, 1),
+        },
+        cache: Spanned::new(2, 2, 3),
+        handle: NotWalkable,
+        retry_count: Spanned::new(3, 4, 5),
+    };
+
+    assert_eq!(paths(&outer), ["type", "retryCount"]);
+}
+
+#[derive(WalkSpans, Unspanned, Respan, ContentHash)]
+#[unspanned(plain = "Labelled<T::Plain>")]
+struct SpannedLabelled<T: Unspanned> {
+    label: Spanned<String>,
+    value: T,
+}
+
+#[derive(Debug, PartialEq)]
+struct Labelled<T> {
+    label: String,
+    value: T,
+}
+
+#[test]
+fn generic_derives() {
+    let labelled = SpannedLabelled {
+        label: Spanned::new(String::from("port"), 0, 6),
+        value: vec![Spanned::new(80_u16, 8, 10)],
+    };
+    let spans = SpanMap::record(&labelled);
+
+    assert_eq!(paths(&labelled), ["label", "value[0]"]);
+
+    let plain = labelled.into_plain();
+    assert_eq!(
+        plain,
+        Labelled {
+            label: String::from("port"),
+            value: vec![80],
+        }
+    );
+
+    let respanned: SpannedLabelled<Vec<Spanned<u16>>> = spans.respan(plain);
+    assert_eq!(respanned.value[0].span(), (8, 10));
+}
+
+#[derive(FieldsMeta)]
+#[serde(rename_all = "kebab-case")]
+#[allow(dead_code)]
+struct Server {
+    /// The address to listen on.
+    #[serde(alias = "host", alias = "address")]
+    listen_addr: String,
+    #[serde(rename = "PORT")]
+    port: u16,
+    #[serde(skip)]
+    cache: Vec<u8>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, String>,
+}
+
+#[test]
+fn fields_meta_uses_serde_names() {
+    assert_eq!(
+        Server::FIELDS,
+        &[
+            FieldMeta {
+                name: "listen-addr",
+                aliases: &["host", "address"],
+                ty: "String",
+                docs: "The address to listen on.",
+            },
+            FieldMeta {
+                name: "PORT",
+                aliases: &[],
+                ty: "u16",
+                docs: "",
+            },
+        ]
+    );
+    assert_eq!(Server::field_meta("address").unwrap().name, "listen-addr");
+    assert!(Server::field_meta("cache").is_none());
+}
+
+#[serde_spanned::with_spans]
+#[derive(Debug, Deserialize)]
+struct WithSpans {
+    #[spanned]
+    password_file: String,
+    retries: u32,
+}
+
+#[test]
+fn with_spans_wraps_marked_fields() {
+    let src = r#"{"password_file": "secret.txt", "retries": 3}"#;
+
+    let value: WithSpans = common::from_str_tree(src).unwrap();
+
+    assert_eq!(value.password_file(), "secret.txt");
+    let (start, end) = value.password_file_span();
+    assert_eq!(&src[start..end], r#""secret.txt""#);
+    assert_eq!(value.retries, 3);
+}
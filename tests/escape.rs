@@ -0,0 +1,84 @@
+#![cfg(feature = "alloc")]
+
+use serde_spanned::{EscapeMap, Spanned};
+
+/// The map for the string literal starting at `literal` in `src`.
+fn map(src: &str, literal: &str) -> EscapeMap {
+    let start = src.find(literal).unwrap();
+    let spanned = Spanned::new((), start, start + literal.len());
+
+    EscapeMap::for_string(src, &spanned).unwrap()
+}
+
+/// The source text for each decoded character.
+fn chars<'a>(src: &'a str, map: &EscapeMap) -> Vec<&'a str> {
+    (0..map.len())
+        .map(|i| &src[map.span(i..i + 1).unwrap().as_range()])
+        .collect()
+}
+
+#[test]
+fn double_quoted_escapes() {
+    let src = r#"key = "a\n\u00e9\"""#;
+
+    let map = map(src, r#""a\n\u00e9\"""#);
+
+    assert_eq!(chars(src, &map), ["a", r"\n", r"\u00e9", r#"\""#]);
+}
+
+#[test]
+fn single_quoted_strings_have_no_escapes() {
+    let src = r"path = 'C:\new\u00e9'";
+
+    let map = map(src, r"'C:\new\u00e9'");
+
+    assert_eq!(map.len(), r"C:\new\u00e9".len());
+    assert_eq!(chars(src, &map)[2], r"\");
+    assert_eq!(chars(src, &map)[3], "n");
+}
+
+#[test]
+fn multi_line_basic_strings_strip_the_delimiters() {
+    let src = "text = \"\"\"\nab\\tc\"\"\"";
+
+    let map = map(src, "\"\"\"\nab\\tc\"\"\"");
+
+    // the line break after the opening delimiter isn't part of the value
+    assert_eq!(chars(src, &map), ["a", "b", "\\t", "c"]);
+    assert_eq!(map.offset(4), Some(src.len() - 3));
+}
+
+#[test]
+fn multi_line_literal_strings_strip_the_delimiters() {
+    let src = "text = '''\r\na\\b\n'''";
+
+    let map = map(src, "'''\r\na\\b\n'''");
+
+    assert_eq!(chars(src, &map), ["a", "\\", "b", "\n"]);
+}
+
+#[test]
+fn line_ending_backslash_decodes_to_nothing() {
+    let src = "text = \"\"\"\nThe quick \\  \n\n    brown \\\r\n  fox\"\"\"";
+    let decoded = "The quick brown fox";
+
+    let map = map(src, &src[7..]);
+
+    assert_eq!(map.len(), decoded.chars().count());
+    let b = decoded.find('b').unwrap();
+    assert_eq!(&src[map.span(b..b + 5).unwrap().as_range()], "brown");
+    let f = decoded.find('f').unwrap();
+    assert_eq!(&src[map.span(f..f + 3).unwrap().as_range()], "fox");
+    // the space before the backslash is kept
+    let space = map.offset(f - 1).unwrap();
+    assert_eq!(&src[space..space + 1], " ");
+}
+
+#[test]
+fn unquoted_text_is_treated_as_escaped() {
+    let src = r"a\tb";
+
+    let map = map(src, src);
+
+    assert_eq!(chars(src, &map), ["a", r"\t", "b"]);
+}
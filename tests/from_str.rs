@@ -0,0 +1,51 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use serde_derive::Deserialize;
+use serde_spanned::SpannedFromStr;
+use std::net::SocketAddr;
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    listen: SpannedFromStr<SocketAddr>,
+}
+
+/// Deserialize `src` with both the tree and streaming deserializers.
+fn both(src: &str) -> Vec<Result<Config, common::Error>> {
+    vec![common::from_str_tree(src), common::from_str_streaming(src)]
+}
+
+#[test]
+fn parsed_values_keep_the_span_of_the_string() {
+    let src = r#"{"listen": "127.0.0.1:80"}"#;
+
+    for result in both(src) {
+        let listen = result.unwrap().listen.into_inner();
+
+        assert_eq!(listen.value(), &"127.0.0.1:80".parse().unwrap());
+        assert_eq!(&src[listen.start()..listen.end()], r#""127.0.0.1:80""#);
+    }
+}
+
+#[test]
+fn parse_errors_include_the_span_of_the_string() {
+    let src = r#"{"listen": "localhost"}"#;
+
+    for result in both(src) {
+        let err = result.unwrap_err();
+
+        assert_eq!(err.0, "invalid socket address syntax (at 11..22)");
+    }
+}
+
+#[test]
+fn values_which_arent_strings_are_rejected() {
+    let src = r#"{"listen": 80}"#;
+
+    for result in both(src) {
+        let err = result.unwrap_err();
+
+        assert!(err.0.contains("expected a string"), "{}", err.0);
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "alloc")]
+
+use serde_spanned::{Columns, LineIndex, Position};
+
+const SOURCES: &[&str] = &[
+    "",
+    "\n",
+    "\r\n",
+    "single line",
+    "trailing newline\n",
+    "a\nb\r\nc\n\nd",
+    "[server]\r\nport = 8080\r\n\r\nhost = \"localhost\"\r\n",
+    "name = \"café\"\nemoji = \"🦀🦀\"\r\ncjk = \"日本語\"",
+    "ends without a newline: ü",
+    "e\u{301}\r\n\u{1F1F3}\u{1F1FF}\n",
+];
+
+/// Every offset in `src`, including ones in the middle of characters and
+/// past the end.
+fn offsets(src: &str) -> impl Iterator<Item = usize> { 0..=src.len() + 2 }
+
+fn check_equivalence(columns: Columns) {
+    for src in SOURCES {
+        let index = LineIndex::new(src).with_columns(columns);
+
+        for offset in offsets(src) {
+            assert_eq!(
+                index.position(offset),
+                Position::from_offset_with_columns(src, offset, columns),
+                "offset {} in {:?}",
+                offset,
+                src
+            );
+        }
+    }
+}
+
+#[test]
+fn line_index_matches_from_offset() { check_equivalence(Columns::Chars); }
+
+#[cfg(feature = "unicode-segmentation")]
+#[test]
+fn line_index_matches_from_offset_with_graphemes() {
+    check_equivalence(Columns::Graphemes);
+}
+
+#[test]
+fn line_index_round_trips_char_boundaries() {
+    for src in SOURCES {
+        let index = LineIndex::new(src);
+
+        for offset in (0..=src.len()).filter(|&ix| src.is_char_boundary(ix)) {
+            // The \r and \n of a \r\n share a position, so map back to the \r
+            let expected = if src[..offset].ends_with('\r')
+                && src[offset..].starts_with('\n')
+            {
+                offset - 1
+            } else {
+                offset
+            };
+
+            assert_eq!(
+                index.offset(index.position(offset)),
+                Some(expected),
+                "offset {} in {:?}",
+                offset,
+                src
+            );
+        }
+    }
+}
+
+#[test]
+fn positions_at_line_ends() {
+    let src = "ab\r\ncafé\nx";
+    let index = LineIndex::new(src);
+
+    // Just after the last character, before the \r\n
+    assert_eq!(index.position(2), Position::new(0, 2));
+    // Between the \r and \n
+    assert_eq!(index.position(3), Position::new(0, 2));
+    // The start of the next line
+    assert_eq!(index.position(4), Position::new(1, 0));
+    // Just after the multibyte é, before the \n
+    assert_eq!(index.position(9), Position::new(1, 4));
+    assert_eq!(index.position(src.len()), Position::new(2, 1));
+
+    assert_eq!(index.offset(Position::new(0, 2)), Some(2));
+    assert_eq!(index.offset(Position::new(0, 3)), None);
+    assert_eq!(index.offset(Position::new(1, 4)), Some(9));
+    assert_eq!(index.offset(Position::new(3, 0)), None);
+}
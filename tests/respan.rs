@@ -0,0 +1,24 @@
+#![cfg(feature = "alloc")]
+
+use serde_spanned::{SpanMap, Spanned, Unspanned};
+use std::collections::BTreeMap;
+
+#[test]
+fn keys_containing_path_separators_keep_their_own_spans() {
+    let mut original: BTreeMap<String, Spanned<Vec<Spanned<u8>>>> =
+        BTreeMap::new();
+    original.insert(
+        String::from("a"),
+        Spanned::new(vec![Spanned::new(1, 4, 5)], 3, 6),
+    );
+    original.insert(String::from("a[0]"), Spanned::new(Vec::new(), 10, 12));
+    let spans = SpanMap::record(&original);
+
+    let respanned: BTreeMap<String, Spanned<Vec<Spanned<u8>>>> =
+        spans.respan(original.into_plain());
+
+    assert_eq!(respanned["a"].span(), (3, 6));
+    assert_eq!(respanned["a"].value()[0].span(), (4, 5));
+    assert_eq!(respanned["a[0]"].span(), (10, 12));
+    assert_eq!(spans.len(), 3);
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "alloc")]
+
+use serde_spanned::{
+    snapshot::{Excerpt, Snapshot},
+    walk::{Path, WalkSpans},
+    Secret, Spanned,
+};
+
+const SRC: &str = "user = \"admin\"\npassword = \"hunter2\"\n";
+
+fn password() -> Spanned<Secret<String>> {
+    Spanned::new(Secret::new(String::from("hunter2")), 26, 35)
+}
+
+#[test]
+fn excerpts_of_secrets_are_redacted() {
+    let password = password();
+
+    let rendered = Excerpt::new(&password, SRC).to_string();
+
+    assert_eq!(rendered, "<redacted> (2:12..2:21) -> Secret(<redacted>)");
+}
+
+#[test]
+fn excerpts_can_be_redacted_explicitly() {
+    let user = Spanned::new(String::from("admin"), 7, 14);
+
+    let rendered = Excerpt::new(&user, SRC).redacted().to_string();
+
+    assert_eq!(rendered, r#"<redacted> (1:8..1:15) -> "admin""#);
+}
+
+#[test]
+fn snapshots_never_contain_the_secret() {
+    let value = (Spanned::new(String::from("admin"), 7, 14), Some(password()));
+
+    let rendered = Snapshot::new(&value, SRC).to_string();
+
+    assert!(!rendered.contains("hunter2"), "{}", rendered);
+    assert_eq!(
+        rendered,
+        "[0]: \"\\\"admin\\\"\" (1:8..1:15)\n[1]: <redacted> (2:12..2:21)\n",
+    );
+}
+
+#[test]
+fn spans_nested_inside_a_secret_are_redacted() {
+    let secret = Secret::new(vec![Spanned::new("hunter2", 26, 35)]);
+    let mut paths = Vec::new();
+
+    secret.walk_spans(&Path::ROOT, &mut |path, _| {
+        paths.push((path.to_string(), path.is_redacted()));
+    });
+
+    assert_eq!(paths, vec![(String::from("[0]"), true)]);
+    assert!(!Snapshot::new(&secret, SRC).to_string().contains("hunter2"));
+}
@@ -0,0 +1,68 @@
+use serde_spanned::Span;
+
+fn lines(span: Span, src: &str) -> Vec<&str> {
+    span.lines(src).map(|line| &src[line.as_range()]).collect()
+}
+
+#[test]
+fn lines_without_a_trailing_line_break() {
+    let src = "first\nsecond\nthird";
+
+    assert_eq!(
+        lines(Span::new(0, src.len()), src),
+        ["first", "second", "third"]
+    );
+    assert_eq!(lines(Span::new(2, 9), src), ["rst", "sec"]);
+}
+
+#[test]
+fn span_ending_after_a_line_break_has_no_trailing_empty_line() {
+    let src = "first\nsecond\r\nthird";
+
+    assert_eq!(lines(Span::new(0, 6), src), ["first"]);
+    assert_eq!(lines(Span::new(0, 14), src), ["first", "second"]);
+}
+
+#[test]
+fn span_ending_before_a_line_break() {
+    let src = "first\r\nsecond";
+
+    assert_eq!(lines(Span::new(0, 5), src), ["first"]);
+    assert_eq!(lines(Span::new(0, 6), src), ["first"]);
+}
+
+#[test]
+fn empty_lines_inside_a_span_are_kept() {
+    let src = "a\n\n\nb\n";
+
+    assert_eq!(lines(Span::new(0, src.len()), src), ["a", "", "", "b"]);
+}
+
+#[test]
+fn empty_spans_yield_a_single_empty_line() {
+    let src = "a\nb";
+
+    assert_eq!(
+        Span::new(2, 2).lines(src).collect::<Vec<_>>(),
+        [Span::new(2, 2)]
+    );
+    assert_eq!(
+        Span::new(9, 9).lines(src).collect::<Vec<_>>(),
+        [Span::new(3, 3)]
+    );
+}
+
+#[test]
+fn try_new_checks_the_clamped_end() {
+    assert_eq!(
+        Span::try_new(0, usize::MAX),
+        Ok(Span::new(0, usize::MAX - 1))
+    );
+    assert_eq!(
+        Span::try_new(usize::MAX - 1, usize::MAX),
+        Ok(Span::new(usize::MAX - 1, usize::MAX - 1))
+    );
+
+    let err = Span::try_new(usize::MAX, usize::MAX).unwrap_err();
+    assert_eq!((err.start(), err.end()), (usize::MAX, usize::MAX - 1));
+}
@@ -0,0 +1,548 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use serde::de::{value::Error, Deserialize, IntoDeserializer};
+use serde_derive::Deserialize;
+use serde_spanned::{
+    value::{Kind, Limits, MergeStrategy, Number, Symbol, Value},
+    walk::Path,
+    Spanned, SpannedValue, Unspanned, WalkSpans,
+};
+
+const SRC: &str =
+    r#"{"name": "demo", "ports": [80, -1], "debug": null, "b": true}"#;
+
+fn text<T>(spanned: &Spanned<T>) -> &'static str {
+    let (start, end) = spanned.span();
+    &SRC[start..end]
+}
+
+#[test]
+fn every_node_has_a_span() {
+    for value in &[
+        common::from_str_tree::<Spanned<SpannedValue>>(SRC).unwrap(),
+        common::from_str_streaming::<Spanned<SpannedValue>>(SRC).unwrap(),
+    ] {
+        assert_eq!(text(value), SRC);
+
+        let (key, name) = value.value().get_entry("name").unwrap();
+        assert_eq!(text(key), r#""name""#);
+        assert_eq!(text(name), r#""demo""#);
+        assert_eq!(name.value().as_str(), Some("demo"));
+
+        let ports = value.value().get("ports").unwrap();
+        assert_eq!(text(ports), "[80, -1]");
+        let ports = ports.value().as_array().unwrap();
+        assert_eq!(text(&ports[1]), "-1");
+        assert_eq!(ports[1].value().as_number(), Some(Number::Signed(-1)));
+
+        assert!(value.value().get("debug").unwrap().value().is_null());
+        assert!(value.value().get("missing").is_none());
+    }
+}
+
+#[test]
+fn objects_keep_document_order() {
+    let value: SpannedValue = common::from_str_tree(SRC).unwrap();
+
+    let keys: Vec<&str> = value
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(key, _)| key.value().as_str())
+        .collect();
+
+    assert_eq!(keys, ["name", "ports", "debug", "b"]);
+}
+
+#[test]
+fn duplicate_keys_are_rejected() {
+    let src = r#"{"a": 1, "b": 2, "a": 3}"#;
+
+    let err = common::from_str_tree::<SpannedValue>(src).unwrap_err();
+
+    assert_eq!(err.0, "duplicate key `a` (at 17..20)");
+}
+
+#[test]
+fn large_objects_keep_document_order() {
+    // Big enough that checking every key against every other would be
+    // noticeably slow
+    let keys: Vec<String> =
+        (0..50_000).rev().map(|i| format!("k{}", i)).collect();
+    let entries: Vec<String> =
+        keys.iter().map(|key| format!("{:?}: 0", key)).collect();
+    let src = format!("{{{}}}", entries.join(", "));
+
+    let value: SpannedValue = common::from_str_streaming(&src).unwrap();
+
+    let got: Vec<&str> = value
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(key, _)| key.value().as_str())
+        .collect();
+    assert_eq!(got, keys);
+}
+
+/// Deserialize `src` with both test deserializers and the given limits.
+fn with_limits(
+    src: &str,
+    limits: &Limits,
+) -> Vec<Result<SpannedValue, String>> {
+    let mut stream = common::Stream::new(src);
+    let streaming = SpannedValue::deserialize_with_limits(&mut stream, limits);
+    let node = common::parse(src).unwrap();
+    let tree = SpannedValue::deserialize_with_limits(&node, limits);
+
+    vec![tree, streaming]
+        .into_iter()
+        .map(|result| result.map_err(|e| e.0))
+        .collect()
+}
+
+#[test]
+fn default_limits_reject_deep_nesting() {
+    let src = format!("{}{}", "[".repeat(200), "]".repeat(200));
+
+    for result in with_limits(&src, &Limits::default()) {
+        let err = result.unwrap_err();
+
+        assert_eq!(
+            err,
+            "a value is nested more than 128 levels deep (at 129..271)"
+        );
+    }
+}
+
+#[test]
+fn nesting_up_to_the_limit_is_allowed() {
+    let limits = Limits {
+        max_depth: 2,
+        ..Limits::default()
+    };
+
+    for result in with_limits("[[1], 2]", &limits) {
+        assert!(result.is_ok());
+    }
+    for result in with_limits(r#"{"a": [{"b": 1}]}"#, &limits) {
+        assert_eq!(
+            result.unwrap_err(),
+            "a value is nested more than 2 levels deep (at 13..14)"
+        );
+    }
+}
+
+#[test]
+fn node_count_limit() {
+    let limits = Limits {
+        max_nodes: 3,
+        ..Limits::default()
+    };
+
+    for result in with_limits("[1, 2]", &limits) {
+        assert!(result.is_ok());
+    }
+    for result in with_limits("[1, 2, [3]]", &limits) {
+        assert_eq!(
+            result.unwrap_err(),
+            "the document has more than 3 values (at 7..10)"
+        );
+    }
+}
+
+#[test]
+fn string_length_limit_applies_to_keys_and_values() {
+    let limits = Limits {
+        max_string_len: 3,
+        ..Limits::default()
+    };
+
+    for result in with_limits(r#"{"abc": "xyz"}"#, &limits) {
+        assert!(result.is_ok());
+    }
+    for result in with_limits(r#"{"abc": "long"}"#, &limits) {
+        assert_eq!(
+            result.unwrap_err(),
+            "a string is longer than 3 bytes (at 8..14)"
+        );
+    }
+    for result in with_limits(r#"{"long": 1}"#, &limits) {
+        assert_eq!(
+            result.unwrap_err(),
+            "a string is longer than 3 bytes (at 1..7)"
+        );
+    }
+    for result in with_limits(r#""long""#, &limits) {
+        assert_eq!(result.unwrap_err(), "a string is longer than 3 bytes");
+    }
+}
+
+#[test]
+fn unlimited() {
+    let src = format!("{}{}", "[".repeat(200), "]".repeat(200));
+
+    for result in with_limits(&src, &Limits::UNLIMITED) {
+        assert!(result.is_ok());
+    }
+}
+
+#[test]
+fn walk_uses_keys_and_indices() {
+    let value: SpannedValue = common::from_str_tree(SRC).unwrap();
+    let mut paths = Vec::new();
+
+    value.walk_spans(&Path::ROOT, &mut |path, (start, end)| {
+        paths.push((path.to_string(), &SRC[start..end]));
+    });
+
+    assert_eq!(
+        paths,
+        vec![
+            (String::from("name"), r#""demo""#),
+            (String::from("ports"), "[80, -1]"),
+            (String::from("ports[0]"), "80"),
+            (String::from("ports[1]"), "-1"),
+            (String::from("debug"), "null"),
+            (String::from("b"), "true"),
+        ]
+    );
+}
+
+#[test]
+fn into_plain_strips_every_span() {
+    let value: Spanned<SpannedValue> = common::from_str_tree(SRC).unwrap();
+
+    let plain = value.into_plain();
+
+    assert_eq!(
+        plain,
+        Value::Object(vec![
+            (String::from("name"), Value::String(String::from("demo"))),
+            (
+                String::from("ports"),
+                Value::Array(vec![
+                    Value::Number(Number::Unsigned(80)),
+                    Value::Number(Number::Signed(-1)),
+                ])
+            ),
+            (String::from("debug"), Value::Null),
+            (String::from("b"), Value::Bool(true)),
+        ])
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    name: Spanned<String>,
+    ports: Vec<Spanned<i32>>,
+    debug: Option<bool>,
+    b: bool,
+}
+
+#[test]
+fn deserialize_a_typed_struct_with_the_original_spans() {
+    let value: Spanned<SpannedValue> = common::from_str_tree(SRC).unwrap();
+
+    let config = Config::deserialize(
+        IntoDeserializer::<Error>::into_deserializer(value),
+    )
+    .unwrap();
+
+    assert_eq!(text(&config.name), r#""demo""#);
+    assert_eq!(text(&config.ports[1]), "-1");
+    assert_eq!(config.debug, None);
+    assert!(config.b);
+}
+
+#[test]
+fn root_without_a_span_cant_be_spanned() {
+    let value: SpannedValue = common::from_str_tree("1").unwrap();
+
+    let err = Spanned::<u32>::deserialize(value.into_deserializer::<Error>())
+        .unwrap_err();
+
+    assert_eq!(err.to_string(), "the root value doesn't have a span");
+}
+
+#[test]
+fn count_nodes_and_heap_memory() {
+    let value: SpannedValue = common::from_str_tree(SRC).unwrap();
+
+    // the object, "demo", the array and its 2 items, null, and true
+    assert_eq!(value.node_count(), 7);
+    assert_eq!(SpannedValue::Null.node_count(), 1);
+
+    let entries = value.as_object().unwrap();
+    let ports = value.get("ports").unwrap().value().as_array().unwrap();
+    let strings = "namedemoportsdebugb".len();
+    assert!(
+        value.heap_size()
+            >= std::mem::size_of_val(entries)
+                + std::mem::size_of_val(ports)
+                + strings
+    );
+    assert_eq!(SpannedValue::from(true).heap_size(), 0);
+}
+
+#[test]
+fn outline_lists_every_key_and_element() {
+    let value: SpannedValue = common::from_str_tree(SRC).unwrap();
+
+    let outline = value.outline();
+
+    let summary: Vec<_> = outline
+        .iter()
+        .map(|symbol| {
+            (symbol.name.as_str(), symbol.kind, symbol.children.len())
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("name", Kind::String, 0),
+            ("ports", Kind::Array, 2),
+            ("debug", Kind::Null, 0),
+            ("b", Kind::Bool, 0),
+        ]
+    );
+
+    let ports = &outline[1];
+    assert_eq!(&SRC[ports.name_span.as_range()], r#""ports""#);
+    assert_eq!(&SRC[ports.span.as_range()], "[80, -1]");
+    assert_eq!(&SRC[ports.full_span().as_range()], r#""ports": [80, -1]"#);
+    assert_eq!(
+        ports.children[1],
+        Symbol {
+            name: String::from("1"),
+            name_span: ports.children[1].span,
+            span: ports.children[1].span,
+            kind: Kind::Number,
+            children: Vec::new(),
+        }
+    );
+    assert_eq!(&SRC[ports.children[1].span.as_range()], "-1");
+}
+
+#[test]
+fn hover_finds_the_innermost_value() {
+    let value: SpannedValue = common::from_str_tree(SRC).unwrap();
+
+    let minus_one = SRC.find("-1").unwrap();
+    let hover = value.hover(minus_one + 1).unwrap();
+    assert_eq!(hover.path, "ports[1]");
+    assert!(hover.key.is_none());
+    assert_eq!(text(hover.value), "-1");
+
+    // between the array's elements
+    let hover = value.hover(minus_one - 1).unwrap();
+    assert_eq!(hover.path, "ports");
+    assert_eq!(text(hover.key.unwrap()), r#""ports""#);
+    assert_eq!(text(hover.value), "[80, -1]");
+
+    // on a key
+    let hover = value.hover(SRC.find("debug").unwrap()).unwrap();
+    assert_eq!(hover.path, "debug");
+    assert!(hover.value.value().is_null());
+
+    // outside every entry
+    assert!(value.hover(0).is_none());
+    assert!(value.hover(SRC.len()).is_none());
+}
+
+#[test]
+fn look_up_nested_values_by_path() {
+    let src = r#"{"server": {"ports": [80, [443]], "a.b": 1, "x/y~": 2}}"#;
+    let mut value: SpannedValue = common::from_str_tree(src).unwrap();
+    let text = |spanned: &Spanned<SpannedValue>| {
+        let (start, end) = spanned.span();
+        &src[start..end]
+    };
+
+    assert_eq!(text(value.get_path("server.ports").unwrap()), "[80, [443]]");
+    assert_eq!(text(value.get_path("server.ports[1][0]").unwrap()), "443");
+    assert_eq!(text(value.pointer("/server/ports/1/0").unwrap()), "443");
+    assert_eq!(text(value.pointer("/server/a.b").unwrap()), "1");
+    assert_eq!(text(value.pointer("/server/x~1y~0").unwrap()), "2");
+
+    let ports = value.get("server").unwrap().value().get("ports").unwrap();
+    assert_eq!(text(ports.value().get_index(0).unwrap()), "80");
+    assert!(ports.value().get_index(2).is_none());
+
+    for missing in &[
+        "",
+        "server.",
+        "server.ports[2]",
+        "server.ports[01]",
+        "server[0]",
+        "server.ports[0",
+    ] {
+        assert!(value.get_path(missing).is_none(), "{}", missing);
+    }
+    for missing in &[
+        "",
+        "server",
+        "/server/ports/01",
+        "/server/ports/+1",
+        "/nope",
+    ] {
+        assert!(value.pointer(missing).is_none(), "{}", missing);
+    }
+
+    *value.pointer_mut("/server/ports/0").unwrap().value_mut() =
+        SpannedValue::from(8080_u64);
+    *value
+        .get_path_mut("server.ports[1][0]")
+        .unwrap()
+        .value_mut() = SpannedValue::from(8443_u64);
+
+    let ports = value.get_path("server.ports").unwrap().value();
+    assert_eq!(
+        ports.get_index(0).unwrap().value().as_number(),
+        Some(Number::Unsigned(8080))
+    );
+    assert_eq!(
+        value
+            .pointer("/server/ports/1/0")
+            .unwrap()
+            .value()
+            .as_number(),
+        Some(Number::Unsigned(8443))
+    );
+}
+
+#[test]
+fn arrays_can_be_indexed_at_the_root() {
+    let value: SpannedValue = common::from_str_tree("[{\"a\": 1}]").unwrap();
+
+    assert!(value.get_path("[0].a").is_some());
+    assert!(value.pointer("/0/a").is_some());
+}
+
+#[test]
+fn walk_every_value_depth_first() {
+    let value: SpannedValue = common::from_str_tree(SRC).unwrap();
+    let mut walked = Vec::new();
+
+    for (path, node) in value.walk() {
+        walked.push((path, text(node)));
+    }
+
+    // the same order as WalkSpans
+    let mut expected = Vec::new();
+    value.walk_spans(&Path::ROOT, &mut |path, (start, end)| {
+        expected.push((path.to_string(), &SRC[start..end]));
+    });
+    assert_eq!(walked, expected);
+}
+
+#[test]
+fn scalars_and_strings() {
+    let value: SpannedValue = common::from_str_tree(SRC).unwrap();
+
+    let scalars: Vec<_> = value.scalars().map(|(path, _)| path).collect();
+    assert_eq!(scalars, ["name", "ports[0]", "ports[1]", "debug", "b"]);
+
+    let strings: Vec<_> = value.strings().collect();
+    assert_eq!(strings.len(), 1);
+    assert_eq!(strings[0].0, "name");
+    assert_eq!(*strings[0].1.value(), "demo");
+    assert_eq!(text(&strings[0].1), r#""demo""#);
+}
+
+#[test]
+fn iterate_over_children() {
+    let mut value: SpannedValue = common::from_str_tree(SRC).unwrap();
+
+    let children: Vec<_> = value.iter().map(text).collect();
+    assert_eq!(children, [r#""demo""#, "[80, -1]", "null", "true"]);
+    assert_eq!(SpannedValue::Null.iter().count(), 0);
+
+    for child in value.iter_mut() {
+        *child.value_mut() = SpannedValue::Null;
+    }
+    assert!(value.iter().all(|child| child.value().is_null()));
+    // only the values were replaced, so they keep their spans
+    assert_eq!(text(value.get_path("ports").unwrap()), "[80, -1]");
+}
+
+#[test]
+fn merge_objects_key_by_key() {
+    let base_src =
+        r#"{"server": {"host": "a", "ports": [80]}, "debug": false}"#;
+    let overrides_src =
+        r#"{"server": {"ports": [443], "tls": true}, "debug": true}"#;
+    let base: SpannedValue = common::from_str_tree(base_src).unwrap();
+    let overrides: SpannedValue = common::from_str_tree(overrides_src).unwrap();
+
+    let mut replaced = base.clone();
+    replaced.merge(overrides.clone(), MergeStrategy::Replace);
+
+    let plain = |value: &SpannedValue| value.clone().into_plain();
+    let ints = |items: &[u64]| {
+        Value::Array(
+            items
+                .iter()
+                .map(|&n| Value::Number(Number::Unsigned(n)))
+                .collect(),
+        )
+    };
+    assert_eq!(
+        plain(&replaced),
+        Value::Object(vec![
+            (
+                String::from("server"),
+                Value::Object(vec![
+                    (String::from("host"), Value::String(String::from("a"))),
+                    (String::from("ports"), ints(&[443])),
+                    (String::from("tls"), Value::Bool(true)),
+                ])
+            ),
+            (String::from("debug"), Value::Bool(true)),
+        ])
+    );
+
+    // every value keeps the span from the document it came from
+    let span_in = |src: &'static str, path: &str, value: &SpannedValue| {
+        let (start, end) = value.get_path(path).unwrap().span();
+        &src[start..end]
+    };
+    assert_eq!(span_in(base_src, "server.host", &replaced), r#""a""#);
+    assert_eq!(span_in(overrides_src, "server.ports", &replaced), "[443]");
+    assert_eq!(span_in(overrides_src, "debug", &replaced), "true");
+    let (key, _) = replaced.get_entry("debug").unwrap();
+    assert_eq!(&overrides_src[key.start()..key.end()], r#""debug""#);
+    // merged objects keep the existing span
+    assert_eq!(
+        span_in(base_src, "server", &replaced),
+        r#"{"host": "a", "ports": [80]}"#
+    );
+
+    let mut appended = base;
+    appended.merge(overrides, MergeStrategy::Append);
+    assert_eq!(
+        plain(appended.get_path("server.ports").unwrap().value()),
+        ints(&[80, 443])
+    );
+    assert_eq!(span_in(base_src, "server.ports[0]", &appended), "80");
+    assert_eq!(span_in(overrides_src, "server.ports[1]", &appended), "443");
+}
+
+#[test]
+fn merge_replaces_values_of_a_different_kind() {
+    let mut value: SpannedValue =
+        common::from_str_tree(r#"{"a": [1]}"#).unwrap();
+
+    value.merge(
+        common::from_str_tree(r#"{"a": {"b": 2}}"#).unwrap(),
+        MergeStrategy::Append,
+    );
+    assert_eq!(
+        value.get_path("a.b").unwrap().value().as_number(),
+        Some(Number::Unsigned(2))
+    );
+
+    value.merge(SpannedValue::from(true), MergeStrategy::Append);
+    assert_eq!(value, SpannedValue::Bool(true));
+}
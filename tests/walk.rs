@@ -0,0 +1,116 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use serde_spanned::{
+    walk::{check_nesting, folding_ranges, Path},
+    Span, Spanned, SpannedValue, WalkSpans,
+};
+use std::collections::BTreeMap;
+
+#[test]
+fn keys_containing_path_separators_are_siblings() {
+    let mut map = BTreeMap::new();
+    map.insert("a", Spanned::new(1, 1, 3));
+    map.insert("a.b", Spanned::new(2, 5, 9));
+    map.insert("a[0]", Spanned::new(3, 10, 14));
+    let value = Spanned::new(map, 0, 20);
+
+    assert!(check_nesting(&value).is_ok());
+}
+
+#[test]
+fn nesting_errors_inside_keys_containing_path_separators() {
+    let mut inner = BTreeMap::new();
+    inner.insert("c.d", Spanned::new(1, 12, 14));
+    let mut map = BTreeMap::new();
+    map.insert("a.b", Spanned::new(inner, 1, 10));
+    let value = Spanned::new(map, 0, 20);
+
+    let err = check_nesting(&value).unwrap_err();
+
+    assert_eq!(err.to_string(), "a.b.c.d (12..14) isn't inside a.b (1..10)");
+}
+
+fn walked<W: WalkSpans>(value: &W) -> Vec<(String, (usize, usize))> {
+    let mut spans = Vec::new();
+    value.walk_spans(&Path::ROOT, &mut |path, span| {
+        spans.push((path.to_string(), span))
+    });
+    spans
+}
+
+#[test]
+fn arrays_are_walked_like_slices() {
+    let value = [Spanned::new(1, 1, 2), Spanned::new(2, 4, 5)];
+
+    assert_eq!(
+        walked(&value),
+        [("[0]".to_string(), (1, 2)), ("[1]".to_string(), (4, 5))]
+    );
+    assert!(walked(&[(); 0]).is_empty());
+}
+
+#[test]
+fn map_keys_are_not_walked() {
+    let mut map = BTreeMap::new();
+    map.insert(Spanned::new("a", 1, 4), Spanned::new(1, 6, 7));
+
+    assert_eq!(walked(&map), [("a".to_string(), (6, 7))]);
+}
+
+const CONFIG: &str = r#"{
+  "server": {
+    "tls": {
+      "cert": "a.pem"
+    },
+    "ports": [80, 443]
+  },
+  "name": "x"
+}"#;
+
+fn folded(min_lines: usize) -> Vec<&'static str> {
+    let config: Spanned<SpannedValue> = common::from_str_tree(CONFIG).unwrap();
+
+    folding_ranges(&config, CONFIG, min_lines)
+        .into_iter()
+        .map(|span| &CONFIG[span.as_range()])
+        .collect()
+}
+
+#[test]
+fn folding_ranges_for_nested_multi_line_tables() {
+    let got = folded(2);
+
+    assert_eq!(got.len(), 3);
+    assert_eq!(got[0], CONFIG);
+    assert!(got[1].starts_with("{\n    \"tls\""));
+    assert_eq!(got[2], "{\n      \"cert\": \"a.pem\"\n    }");
+}
+
+#[test]
+fn folding_ranges_below_min_lines_are_skipped() {
+    assert_eq!(folded(4).len(), 2);
+    assert_eq!(folded(9), [CONFIG]);
+    assert!(folded(10).is_empty());
+}
+
+#[test]
+fn folding_range_ending_in_a_newline() {
+    let src = "a\nb\nc";
+
+    // The span covers "a\n", which is a single line
+    let single = Spanned::new(1, 0, 2);
+    assert!(folding_ranges(&single, src, 2).is_empty());
+
+    let double = Spanned::new(1, 0, 4);
+    assert_eq!(folding_ranges(&double, src, 2), [Span::new(0, 4)]);
+}
+
+#[test]
+fn inverted_spans_are_skipped() {
+    let src = "a\nb\nc";
+    let value = Spanned::new(1, 4, 0);
+
+    assert!(folding_ranges(&value, src, 1).is_empty());
+}